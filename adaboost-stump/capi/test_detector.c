@@ -0,0 +1,141 @@
+/* Exercises the C API: loads a model, scans an image given with and without
+ * row padding, checks that both give the same detections and that invalid
+ * arguments fail cleanly.
+ *
+ * usage: test_detector MODEL [IMAGE.pgm]
+ * without an image a synthetic gradient is scanned */
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "adaboost_stump.h"
+
+#define PADDING 13
+
+static int failures = 0;
+
+static void check(int condition, const char *what) {
+    if (!condition) {
+        fprintf(stderr, "FAILED: %s (%s)\n", what, adaboost_last_error());
+        failures++;
+    }
+}
+
+/* binary PGM with 8-bit samples */
+static uint8_t *read_pgm(const char *path, uint32_t *width, uint32_t *height) {
+    FILE *f = fopen(path, "rb");
+    unsigned int max_value;
+    uint8_t *pixels;
+
+    if (!f) {
+        return NULL;
+    }
+
+    if (fscanf(f, "P5 %u %u %u", width, height, &max_value) != 3 || max_value > 255) {
+        fclose(f);
+        return NULL;
+    }
+    fgetc(f);
+
+    pixels = malloc((size_t)*width * *height);
+    if (fread(pixels, 1, (size_t)*width * *height, f) != (size_t)*width * *height) {
+        free(pixels);
+        pixels = NULL;
+    }
+
+    fclose(f);
+    return pixels;
+}
+
+static uint8_t *synthetic_image(uint32_t width, uint32_t height) {
+    uint8_t *pixels = malloc((size_t)width * height);
+    uint32_t x, y;
+
+    for (y = 0; y < height; y++) {
+        for (x = 0; x < width; x++) {
+            pixels[y * width + x] = (uint8_t)((x * 7 + y * 3 + (x * y) % 17) % 256);
+        }
+    }
+
+    return pixels;
+}
+
+int main(int argc, char **argv) {
+    AdaboostDetector *detector;
+    AdaboostDetections *detections, *padded_detections;
+    AdaboostDetection detection, padded_detection;
+    uint32_t width = 160, height = 120, y;
+    uint8_t *pixels, *padded;
+    size_t i, count;
+
+    if (argc < 2) {
+        fprintf(stderr, "usage: %s MODEL [IMAGE.pgm]\n", argv[0]);
+        return 2;
+    }
+
+    check(adaboost_abi_version() == ADABOOST_ABI_VERSION, "header and library versions match");
+
+    check(adaboost_detector_load("does-not-exist.json") == NULL, "missing model is refused");
+    printf("Missing model: %s\n", adaboost_last_error());
+
+    detector = adaboost_detector_load(argv[1]);
+    check(detector != NULL, "model loads");
+    if (!detector) {
+        return 1;
+    }
+
+    pixels = argc > 2 ? read_pgm(argv[2], &width, &height) : synthetic_image(width, height);
+    check(pixels != NULL, "image loads");
+    if (!pixels) {
+        return 1;
+    }
+
+    /* the same image with every row followed by PADDING bytes of garbage */
+    padded = malloc((size_t)(width + PADDING) * height);
+    memset(padded, 0xab, (size_t)(width + PADDING) * height);
+    for (y = 0; y < height; y++) {
+        memcpy(padded + y * (width + PADDING), pixels + y * width, width);
+    }
+
+    detections = adaboost_detect(detector, pixels, width, height, width, 1);
+    padded_detections = adaboost_detect(detector, padded, width, height, width + PADDING, 1);
+    check(detections != NULL && padded_detections != NULL, "detection runs");
+
+    count = adaboost_detections_count(detections);
+    check(count == adaboost_detections_count(padded_detections), "stride does not change results");
+    printf("%ux%u image: %zu detections\n", width, height, count);
+
+    for (i = 0; i < count; i++) {
+        check(adaboost_detections_get(detections, i, &detection) == 0, "detection can be read");
+        check(adaboost_detections_get(padded_detections, i, &padded_detection) == 0,
+              "padded detection can be read");
+        check(memcmp(&detection, &padded_detection, sizeof(detection)) == 0,
+              "padded detection is the same");
+
+        printf("  x=%u y=%u %ux%u neighbours=%u score=%.3f\n",
+               detection.x, detection.y, detection.width, detection.height,
+               detection.neighbours, detection.score);
+    }
+
+    check(adaboost_detections_get(detections, count, &detection) == -1, "index past the end fails");
+    check(adaboost_detect(detector, pixels, width, height, width - 1, 1) == NULL,
+          "stride shorter than a row is refused");
+    printf("Short stride: %s\n", adaboost_last_error());
+    check(adaboost_detect(detector, NULL, width, height, width, 1) == NULL, "null pixels are refused");
+
+    adaboost_detections_free(detections);
+    adaboost_detections_free(padded_detections);
+    adaboost_detections_free(NULL);
+    adaboost_detector_free(detector);
+    adaboost_detector_free(NULL);
+    free(pixels);
+    free(padded);
+
+    if (failures) {
+        fprintf(stderr, "%d checks failed\n", failures);
+        return 1;
+    }
+
+    printf("All checks passed\n");
+    return 0;
+}
@@ -0,0 +1,115 @@
+#ifndef ADABOOST_STUMP_H
+#define ADABOOST_STUMP_H
+
+/* Generated from src/capi.rs with cbindgen --config cbindgen.toml --output include/adaboost_stump.h. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * bumped whenever a signature or struct of the header changes
+ */
+#define ADABOOST_ABI_VERSION 1
+
+/**
+ * the grouped detections of one image
+ */
+typedef struct AdaboostDetections AdaboostDetections;
+
+/**
+ * a loaded model
+ */
+typedef struct AdaboostDetector AdaboostDetector;
+
+/**
+ * one grouped detection, in pixels of the image passed in
+ */
+typedef struct {
+  uint32_t x;
+  uint32_t y;
+  uint32_t width;
+  uint32_t height;
+  uint32_t neighbours;
+  double score;
+} AdaboostDetection;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * ADABOOST_ABI_VERSION of the library, to compare with that of the header
+ */
+uint32_t adaboost_abi_version(void);
+
+/**
+ * message of the last failure on this thread, valid until the next failure
+ */
+const char *adaboost_last_error(void);
+
+/**
+ * null if the model cannot be read
+ *
+ * # Safety
+ *
+ * `model_path` is null or a nul-terminated string
+ */
+AdaboostDetector *adaboost_detector_load(const char *model_path);
+
+/**
+ * # Safety
+ *
+ * `detector` is null or came from adaboost_detector_load and was not freed yet
+ */
+void adaboost_detector_free(AdaboostDetector *detector);
+
+/**
+ * scans `height` rows of `width` 8-bit pixels, rows starting `stride` bytes
+ * apart, and groups the windows. null on invalid arguments
+ *
+ * # Safety
+ *
+ * `detector` is null or a live detector, `pixels` is null or points to
+ * `height` rows of `stride` bytes, the last of them at least `width` long
+ */
+AdaboostDetections *adaboost_detect(const AdaboostDetector *detector,
+                                    const uint8_t *pixels,
+                                    uint32_t width,
+                                    uint32_t height,
+                                    size_t stride,
+                                    uint32_t min_neighbours);
+
+/**
+ * # Safety
+ *
+ * `detections` is null or a live list from adaboost_detect
+ */
+size_t adaboost_detections_count(const AdaboostDetections *detections);
+
+/**
+ * copies the detection at `index` into `out`: 0 on success, -1 out of range
+ *
+ * # Safety
+ *
+ * `detections` is null or a live list from adaboost_detect, `out` is null or
+ * points to writable memory for one AdaboostDetection
+ */
+int adaboost_detections_get(const AdaboostDetections *detections,
+                            size_t index,
+                            AdaboostDetection *out);
+
+/**
+ * # Safety
+ *
+ * `detections` is null or came from adaboost_detect and was not freed yet
+ */
+void adaboost_detections_free(AdaboostDetections *detections);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* ADABOOST_STUMP_H */
@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+// side of the face box relative to the eye distance plus the eyes to mouth distance
+pub const FACE_SCALE: f64 = 1.05;
+
+// height of the eyes inside the face box, as a fraction of its side
+pub const EYE_ROW: f64 = 0.3;
+
+pub const NUM_LANDMARKS: usize = 6;
+
+// landmarks of one annotated face, as (x, y) image coordinates
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct FaceFeature {
+    pub left_eye: (f64, f64),
+    pub right_eye: (f64, f64),
+    pub nose: (f64, f64),
+    pub left_mouth: (f64, f64),
+    pub center_mouth: (f64, f64),
+    pub right_mouth: (f64, f64),
+}
+
+impl FaceFeature {
+    pub fn eyes_center(&self) -> (f64, f64) {
+        ((self.left_eye.0 + self.right_eye.0) / 2.0, (self.left_eye.1 + self.right_eye.1) / 2.0)
+    }
+
+    pub fn eye_distance(&self) -> f64 {
+        (self.right_eye.0 - self.left_eye.0).hypot(self.right_eye.1 - self.left_eye.1)
+    }
+
+    // in-plane rotation of the face, zero when the eyes are level
+    pub fn angle(&self) -> f64 {
+        (self.right_eye.1 - self.left_eye.1).atan2(self.right_eye.0 - self.left_eye.0)
+    }
+
+    pub fn face_size(&self) -> f64 {
+        let eyes_center = self.eyes_center();
+        let mouth_distance = (self.center_mouth.0 - eyes_center.0)
+            .hypot(self.center_mouth.1 - eyes_center.1);
+
+        FACE_SCALE * (self.eye_distance() + mouth_distance)
+    }
+
+    // upright square around the face, as (x, y, side)
+    pub fn face_box(&self) -> (f64, f64, f64) {
+        let size = self.face_size();
+        let eyes_center = self.eyes_center();
+        let center_x = (eyes_center.0 + self.center_mouth.0) / 2.0;
+
+        (center_x - size / 2.0, eyes_center.1 - EYE_ROW * size, size)
+    }
+
+    pub fn to_vec(&self) -> Vec<(f64, f64)> {
+        vec![self.left_eye,
+             self.right_eye,
+             self.nose,
+             self.left_mouth,
+             self.center_mouth,
+             self.right_mouth]
+    }
+
+    pub fn from_vec(points: &Vec<(f64, f64)>) -> FaceFeature {
+        FaceFeature {
+            left_eye: points[0],
+            right_eye: points[1],
+            nose: points[2],
+            left_mouth: points[3],
+            center_mouth: points[4],
+            right_mouth: points[5],
+        }
+    }
+}
+
+// reads a CMU/MIT style annotation file: one face per line, made of the image
+// filename followed by the x y pairs of both eyes, the nose and three mouth points
+pub fn get_faces_map(path: &str) -> HashMap<String, Vec<FaceFeature>> {
+    let faces_file = File::open(path)
+        .unwrap_or_else(|error| panic!("Unable to open annotations {}: {}", path, error));
+
+    parse_faces(BufReader::new(faces_file), path)
+}
+
+// `path` only names the annotations in the errors, which give the line
+fn parse_faces<R: BufRead>(reader: R, path: &str) -> HashMap<String, Vec<FaceFeature>> {
+    let mut faces_map = HashMap::new();
+
+    for (i, line) in reader.lines().enumerate() {
+        let l = line.unwrap_or_else(|error| panic!("Unable to read {}: {}", path, error));
+        let tokens: Vec<&str> = l.split_whitespace().collect();
+        if tokens.is_empty() {
+            continue;
+        }
+
+        assert!(tokens.len() == 1 + 2 * NUM_LANDMARKS,
+                "{} line {}: expected a filename and {} coordinates, got {} fields",
+                path,
+                i + 1,
+                2 * NUM_LANDMARKS,
+                tokens.len());
+
+        let filename = tokens[0];
+
+        let coords: Vec<f64> = tokens[1..]
+            .iter()
+            .map(|s| {
+                s.parse()
+                    .unwrap_or_else(|_| panic!("{} line {}: invalid coordinate {}", path, i + 1, s))
+            })
+            .collect();
+
+        let feature = FaceFeature {
+            left_eye: (coords[0], coords[1]),
+            right_eye: (coords[2], coords[3]),
+            nose: (coords[4], coords[5]),
+            left_mouth: (coords[6], coords[7]),
+            center_mouth: (coords[8], coords[9]),
+            right_mouth: (coords[10], coords[11]),
+        };
+
+        let keyname = filename.to_string();
+        let feature_list = faces_map.entry(keyname).or_insert(Vec::new());
+
+        feature_list.push(feature);
+    }
+
+    faces_map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn faces_are_grouped_by_image() {
+        let faces = parse_faces("a.gif 1 2 3 4 5 6 7 8 9 10 11 12\n\
+                                 \n\
+                                 b.gif 0 0 0 0 0 0 0 0 0 0 0 0\n\
+                                 a.gif 2 2 4 2 3 3 2 5 3 5 4 5\n"
+                                    .as_bytes(),
+                                "faces.txt");
+
+        assert_eq!(faces.len(), 2);
+        assert_eq!(faces["a.gif"].len(), 2);
+        assert_eq!(faces["a.gif"][0].left_eye, (1.0, 2.0));
+        assert_eq!(faces["a.gif"][0].right_mouth, (11.0, 12.0));
+        assert_eq!(faces["a.gif"][1].center_mouth, (3.0, 5.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "faces.txt line 2: expected a filename and 12 coordinates, got 7")]
+    fn short_lines_are_reported() {
+        parse_faces("a.gif 1 2 3 4 5 6 7 8 9 10 11 12\nb.gif 1 2 3 4 5 6\n".as_bytes(),
+                    "faces.txt");
+    }
+
+    #[test]
+    #[should_panic(expected = "faces.txt line 1: invalid coordinate x")]
+    fn invalid_coordinates_are_reported() {
+        parse_faces("a.gif 1 2 3 4 5 6 7 8 9 10 11 x\n".as_bytes(), "faces.txt");
+    }
+}
@@ -0,0 +1,21 @@
+use std::env;
+use std::str::FromStr;
+
+// true if `--name` was passed on the command line
+#[allow(dead_code)]
+pub fn get_flag(name: &str) -> bool {
+    let flag = format!("--{}", name);
+    env::args().any(|arg| arg == flag)
+}
+
+// parses the value following `--name`, if it was passed
+#[allow(dead_code)]
+pub fn get_option<T: FromStr>(name: &str) -> Option<T> {
+    let flag = format!("--{}", name);
+    let mut args = env::args().skip_while(|arg| *arg != flag).skip(1);
+
+    args.next().map(|value| match value.parse() {
+        Ok(value) => value,
+        Err(_) => panic!("Invalid value for --{}: {}", name, value),
+    })
+}
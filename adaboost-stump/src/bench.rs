@@ -0,0 +1,365 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_json;
+extern crate image;
+extern crate rulinalg;
+extern crate rand;
+
+mod load;
+mod pgm;
+mod manifest;
+mod synthetic;
+mod shared;
+mod geometry;
+mod integral_image;
+mod haar_like_feature;
+mod decision_tree;
+mod lbp_feature;
+mod weak_classifier;
+mod prediction_ensemble;
+mod soft_cascade;
+mod detector;
+mod fast_integral_image;
+mod compiled_cascade;
+mod linear_svm;
+mod hog;
+mod model;
+mod detection_params;
+mod warp;
+mod prefilter;
+mod scanner;
+mod annotations;
+mod boosting;
+mod args;
+
+use std::time::Instant;
+use image::GrayImage;
+use rand::Rng;
+use rulinalg::matrix::Matrix;
+use integral_image::IntegralImage;
+use fast_integral_image::FastIntegralImage;
+use haar_like_feature::HaarLikeFeature;
+use lbp_feature::LbpFeature;
+use weak_classifier::WeakClassifier;
+use prediction_ensemble::PredictionEnsemble;
+use detector::Detector;
+use compiled_cascade::WindowLayout;
+use model::Model;
+use detection_params::{DetectionParams, ScanLevel};
+use scanner::{scan, scan_compiled, scan_windows};
+use boosting::{adaboost, FeaturePool};
+use synthetic::{seeded_rng, synthetic_dataset};
+use shared::{DataPoint, ImageData, WINDOW_HEIGHT, WINDOW_WIDTH};
+
+const RESOLUTIONS: [(u32, u32); 3] = [(160, 120), (320, 240), (640, 480)];
+
+// faces and non-faces each in the synthetic training set
+const NUM_FACES: usize = 100;
+
+// shares of the pool searched per round, compared with the full search
+const FEATURE_FRACTIONS: [f64; 3] = [0.5, 0.2, 0.05];
+const SAMPLING_ROUNDS: usize = 10;
+
+// largest window of the full scans, four times the model
+const FULL_SCAN_MAX_SIZE: usize = 76;
+
+fn milliseconds(start: Instant) -> f64 {
+    let elapsed = start.elapsed();
+    elapsed.as_secs() as f64 * 1e3 + elapsed.subsec_nanos() as f64 / 1e6
+}
+
+// milliseconds per run of `f`, averaged over `runs` runs
+fn time<F: FnMut()>(runs: usize, mut f: F) -> f64 {
+    let start = Instant::now();
+    for _ in 0..runs {
+        f();
+    }
+
+    milliseconds(start) / runs as f64
+}
+
+// smooth gradient with noise on top, the same for every run
+fn synthetic_image(width: u32, height: u32) -> GrayImage {
+    let mut rng = seeded_rng((width * height) as usize);
+
+    let mut pixels = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            let gradient = 64.0 + 128.0 * (x + y) as f64 / (width + height) as f64;
+            pixels.push((gradient + rng.gen_range(-32.0, 32.0)) as u8);
+        }
+    }
+
+    GrayImage::from_raw(width, height, pixels).unwrap()
+}
+
+// the picture as the matrix IntegralImage is built from, scaled to 0..1
+fn image_data(image: &GrayImage) -> ImageData {
+    let pixels: Vec<f64> = image.iter().map(|&pixel| pixel as f64 / 255.0).collect();
+
+    Matrix::new(image.height() as usize, image.width() as usize, pixels)
+}
+
+fn bench_integral_images(runs: usize) {
+    println!("# Integral images");
+
+    for &(width, height) in RESOLUTIONS.iter() {
+        let image = synthetic_image(width, height);
+        let matrix = image_data(&image);
+
+        let build = time(runs, || {
+            IntegralImage::build(&matrix);
+        });
+        let fast_build = time(runs, || {
+            FastIntegralImage::build(&image);
+        });
+
+        println!("{}x{}: IntegralImage::build {:.3} ms, FastIntegralImage::build {:.3} ms \
+                  ({:.1}x)",
+                 width,
+                 height,
+                 build,
+                 fast_build,
+                 build / fast_build);
+    }
+}
+
+fn bench_features(runs: usize, data: &Vec<DataPoint>) {
+    println!("# Feature scoring");
+
+    let haar_features = HaarLikeFeature::generate_all_features();
+    let mut total = 0.0;
+    let haar = time(runs, || for feature in haar_features.iter() {
+        for data_point in data.iter() {
+            total += feature.get_score(&data_point.integral_image);
+        }
+    });
+    println!("HaarLikeFeature::get_score: {:.1} ns ({} features)",
+             haar * 1e6 / (haar_features.len() * data.len()) as f64,
+             haar_features.len());
+
+    let lbp_features = LbpFeature::generate_all_features();
+    let mut codes = 0;
+    let lbp = time(runs, || for feature in lbp_features.iter() {
+        for data_point in data.iter() {
+            codes += feature.get_code(&data_point.integral_image);
+        }
+    });
+    println!("LbpFeature::get_code: {:.1} ns ({} features)",
+             lbp * 1e6 / (lbp_features.len() * data.len()) as f64,
+             lbp_features.len());
+
+    // keeps the scores from being optimized away
+    if total.is_nan() && codes == 0 {
+        println!("{} {}", total, codes);
+    }
+}
+
+fn bench_boosting(runs: usize, data: &Vec<DataPoint>) {
+    println!("# Boosting");
+
+    // the weak learners use up the features they pick, so each run gets a new pool
+    let mut haar = 0.0;
+    let mut lbp = 0.0;
+
+    for _ in 0..runs {
+        let mut feature_pool = FeaturePool::Haar(HaarLikeFeature::generate_all_features());
+        let start = Instant::now();
+        train_stage(1, 1.0, &mut feature_pool, data, 0);
+        haar += milliseconds(start) / runs as f64;
+
+        let mut feature_pool = FeaturePool::Lbp(LbpFeature::generate_all_features());
+        let start = Instant::now();
+        train_stage(1, 1.0, &mut feature_pool, data, 0);
+        lbp += milliseconds(start) / runs as f64;
+    }
+
+    println!("One round of haar stumps on {} windows: {:.1} ms", data.len(), haar);
+    println!("One round of lbp on {} windows: {:.1} ms", data.len(), lbp);
+}
+
+// a stage of haar stumps over the synthetic windows, sampling the pool from
+// `seed`
+fn train_stage(num_rounds: usize,
+               feature_fraction: f64,
+               feature_pool: &mut FeaturePool,
+               data: &Vec<DataPoint>,
+               seed: usize)
+               -> Vec<WeakClassifier> {
+    adaboost(num_rounds,
+             1,
+             1.0,
+             false,
+             true,
+             feature_fraction,
+             feature_pool,
+             data,
+             NUM_FACES,
+             NUM_FACES,
+             &mut seeded_rng(seed))
+}
+
+// share of the windows the vote of `stage` gets wrong
+fn training_error(stage: &Vec<WeakClassifier>, data: &Vec<DataPoint>) -> f64 {
+    let errors = data.iter()
+        .filter(|data_point| {
+            let vote = stage.iter().fold(0.0, |acc, h| acc + h.predict(&data_point.integral_image));
+            data_point.label * vote.signum() < 0.0
+        })
+        .count();
+
+    errors as f64 / data.len() as f64
+}
+
+// milliseconds per round and training error of a stage searching
+// `feature_fraction` of the pool, averaged over `runs` stages sampled from
+// different seeds
+fn sampled_stage(runs: usize, feature_fraction: f64, data: &Vec<DataPoint>) -> (f64, f64) {
+    let mut round_time = 0.0;
+    let mut error = 0.0;
+
+    for run in 0..runs {
+        let mut feature_pool = FeaturePool::Haar(HaarLikeFeature::generate_all_features());
+        let start = Instant::now();
+        let stage = train_stage(SAMPLING_ROUNDS, feature_fraction, &mut feature_pool, data, run);
+        round_time += milliseconds(start) / (stage.len() * runs) as f64;
+        error += training_error(&stage, data) / runs as f64;
+    }
+
+    (round_time, error)
+}
+
+fn bench_feature_sampling(runs: usize, data: &Vec<DataPoint>) {
+    println!("# Feature sampling");
+
+    let (full_time, full_error) = sampled_stage(runs, 1.0, data);
+    println!("Full search: {:.1} ms per round, training error {:.4} after {} rounds",
+             full_time,
+             full_error,
+             SAMPLING_ROUNDS);
+
+    for &feature_fraction in FEATURE_FRACTIONS.iter() {
+        let (round_time, error) = sampled_stage(runs, feature_fraction, data);
+
+        println!("{:.0}% of the pool: {:.1} ms per round ({:.1}x), training error {:.4} \
+                  ({:+.4} against the full search)",
+                 feature_fraction * 100.0,
+                 round_time,
+                 full_time / round_time,
+                 error,
+                 error - full_error);
+    }
+}
+
+fn bench_detection<D: Detector>(runs: usize, detector: &D) {
+    println!("# Detection");
+
+    for &(width, height) in RESOLUTIONS.iter() {
+        let image = synthetic_image(width, height);
+        let cascade = detector.compile(&WindowLayout::new(width as usize + 1, 1.0))
+            .expect("Only cascades of haar or lbp features can be compiled");
+
+        let level = ScanLevel::new(1.0, 1.0, 1.0);
+
+        let mut window_detections = Vec::new();
+        let mut compiled_detections = Vec::new();
+
+        let window_scan = time(runs, || {
+            window_detections = scan_windows(detector, &image, &level, None);
+        });
+        let compiled_scan = time(runs, || {
+            compiled_detections = scan_compiled(&cascade, &image, &level, None);
+        });
+
+        let num_windows = ((width as usize - WINDOW_WIDTH + 1) *
+                           (height as usize - WINDOW_HEIGHT + 1)) as f64;
+
+        println!("{}x{}: {:.2} ms window by window, {:.2} ms compiled ({:.1}x), {:.0} windows/s, \
+                  {} and {} detections",
+                 width,
+                 height,
+                 window_scan,
+                 compiled_scan,
+                 window_scan / compiled_scan,
+                 num_windows / compiled_scan * 1e3,
+                 window_detections.len(),
+                 compiled_detections.len());
+    }
+}
+
+// hides the compiled form of a detector, so that `scan` goes window by window
+struct Uncompiled<'a, D: 'a>(&'a D);
+
+impl<'a, D: Detector> Detector for Uncompiled<'a, D> {
+    fn predict_window(&self, image_data: &ImageData, integral_image: &IntegralImage) -> bool {
+        self.0.predict_window(image_data, integral_image)
+    }
+
+    fn score_window(&self, image_data: &ImageData, integral_image: &IntegralImage) -> Option<f64> {
+        self.0.score_window(image_data, integral_image)
+    }
+}
+
+// every window size from 19 to FULL_SCAN_MAX_SIZE pixels, as detect scans them
+fn bench_full_scan<D: Detector>(runs: usize, detector: &D) {
+    println!("# Full scan up to {}x{} windows", FULL_SCAN_MAX_SIZE, FULL_SCAN_MAX_SIZE);
+
+    let params = DetectionParams { max_size: Some(FULL_SCAN_MAX_SIZE), ..Default::default() };
+
+    for &(width, height) in RESOLUTIONS.iter() {
+        let image = synthetic_image(width, height);
+
+        let mut window_detections = Vec::new();
+        let mut compiled_detections = Vec::new();
+
+        let window_scan = time(runs, || {
+            window_detections = scan(&Uncompiled(detector), &image, None, &params);
+        });
+        let compiled_scan = time(runs, || {
+            compiled_detections = scan(detector, &image, None, &params);
+        });
+
+        println!("{}x{}: {:.1} ms window by window, {:.1} ms compiled ({:.1}x), {} and {} \
+                  detections",
+                 width,
+                 height,
+                 window_scan,
+                 compiled_scan,
+                 window_scan / compiled_scan,
+                 window_detections.len(),
+                 compiled_detections.len());
+    }
+}
+
+fn main() {
+    let runs = args::get_option("runs").unwrap_or(3);
+    let (data, _, _) = synthetic_dataset(0, NUM_FACES, NUM_FACES);
+
+    println!("{} runs per measurement", runs);
+
+    bench_integral_images(runs);
+    bench_features(runs, &data);
+    bench_boosting(runs, &data);
+    bench_feature_sampling(runs, &data);
+
+    // a small cascade trained on the synthetic windows stands in for a real model
+    match args::get_option::<String>("model") {
+        Some(model_path) => {
+            let model = Model::load(&model_path);
+            bench_detection(runs, &model);
+            bench_full_scan(runs, &model);
+        }
+        None => {
+            let mut feature_pool = FeaturePool::Haar(HaarLikeFeature::generate_all_features());
+            let mut ensemble = PredictionEnsemble::new();
+
+            for &num_rounds in [2, 5].iter() {
+                ensemble.push(train_stage(num_rounds, 1.0, &mut feature_pool, &data, 0));
+            }
+
+            bench_detection(runs, &ensemble);
+            bench_full_scan(runs, &ensemble);
+        }
+    }
+}
@@ -0,0 +1,716 @@
+use std::f64;
+use rand::Rng;
+use rulinalg::vector::Vector;
+use haar_like_feature::HaarLikeFeature;
+use decision_tree::{DecisionNode, DecisionTree};
+use lbp_feature::{LbpClassifier, LbpFeature, NUM_LBP_CODES};
+use weak_classifier::WeakClassifier;
+use shared::DataPoint;
+
+// the weighted error of a weak classifier is kept this far from 0 and 1, so
+// that a perfect or a useless one gets a large but finite weight
+const MIN_ERROR: f64 = 1e-10;
+
+// indices of the features a weak learner looks at: all of them, or a random
+// `feature_fraction` of them drawn anew from `rng` at each call
+fn sample_features<R: Rng>(num_features: usize, feature_fraction: f64, rng: &mut R) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..num_features).collect();
+    if feature_fraction >= 1.0 {
+        return indices;
+    }
+
+    // the first `amount` places of a shuffle
+    let amount = ((num_features as f64 * feature_fraction).ceil() as usize)
+        .max(1)
+        .min(num_features);
+    for i in 0..amount {
+        let j = rng.gen_range(i, num_features);
+        indices.swap(i, j);
+    }
+
+    indices.truncate(amount);
+    indices.sort();
+
+    indices
+}
+
+// the feature with the lowest weighted error at its best threshold among
+// `feature_fraction` of the pool; it leaves the pool unless `reuse_features`
+// lets later rounds pick it again
+pub fn weak_learner<R: Rng>(feature_collection: &mut Vec<HaarLikeFeature>,
+                            image_collection: &Vec<DataPoint>,
+                            weights: &Vector<f64>,
+                            reuse_features: bool,
+                            feature_fraction: f64,
+                            rng: &mut R)
+                            -> HaarLikeFeature {
+    let mut error_star = f64::INFINITY;
+    let mut fi_star = None;
+    let mut threshold_star = None;
+
+    for fi in sample_features(feature_collection.len(), feature_fraction, rng) {
+        let ref mut feature_hypothesis = feature_collection[fi];
+
+        let mut scores: Vec<_> = image_collection.iter()
+            .enumerate()
+            .map(|(index, ref data_point)| {
+                (index, feature_hypothesis.get_score(&data_point.integral_image))
+            })
+            .collect();
+
+        scores.sort_by(|&a, &b| {
+            a.1.partial_cmp(&b.1).unwrap_or_else(|| {
+                panic!("{:?} scores NaN on sample {} or {}", feature_hypothesis, a.0, b.0)
+            })
+        });
+
+        let mut error = image_collection.iter()
+            .zip(weights.iter())
+            .fold(0.0, |acc, (data_point, weight)| {
+                if data_point.label * feature_hypothesis.polarity < 0.0 {
+                    acc + weight
+                } else {
+                    acc
+                }
+            });
+
+        let m = scores.len();
+        for xi in 0..(m + 1) {
+            let curr_x = if xi > 0 {
+                scores[xi - 1].1
+            } else {
+                scores[xi].1 - 1.0
+            };
+
+            let next_x = if xi < m {
+                scores[xi].1
+            } else {
+                scores[xi - 1].1 + 1.0
+            };
+
+            let threshold = (curr_x + next_x) / 2.0;
+            feature_hypothesis.threshold = threshold;
+
+            if error < error_star {
+                error_star = error;
+                fi_star = Some(fi);
+                threshold_star = Some(threshold);
+            }
+
+            if xi < m {
+                // computes the error of the next iteration
+                let index = scores[xi].0;
+                let ref data_point = image_collection[index];
+                let weight = weights[index];
+                error += feature_hypothesis.polarity * data_point.label * weight;
+            } else if error < error_star {
+                // check after all error updates
+                error_star = error;
+                fi_star = Some(fi);
+                threshold_star = Some(threshold);
+            }
+        }
+    }
+
+    let mut feature = if reuse_features {
+        feature_collection[fi_star.unwrap()].clone()
+    } else {
+        feature_collection.remove(fi_star.unwrap())
+    };
+    feature.threshold = threshold_star.unwrap();
+
+    feature
+}
+
+pub fn lbp_weak_learner<R: Rng>(feature_collection: &mut Vec<LbpFeature>,
+                                image_collection: &Vec<DataPoint>,
+                                weights: &Vector<f64>,
+                                reuse_features: bool,
+                                feature_fraction: f64,
+                                rng: &mut R)
+                                -> LbpClassifier {
+    let mut error_star = f64::INFINITY;
+    let mut fi_star = None;
+    let mut table_star = None;
+
+    for fi in sample_features(feature_collection.len(), feature_fraction, rng) {
+        let ref feature_hypothesis = feature_collection[fi];
+
+        // weight of the faces and non-faces that fall in each code
+        let mut face_weights = vec![0.0; NUM_LBP_CODES];
+        let mut non_face_weights = vec![0.0; NUM_LBP_CODES];
+
+        for (data_point, &weight) in image_collection.iter().zip(weights.iter()) {
+            let code = feature_hypothesis.get_code(&data_point.integral_image);
+
+            if data_point.label > 0.0 {
+                face_weights[code] += weight;
+            } else {
+                non_face_weights[code] += weight;
+            }
+        }
+
+        // each code votes for the label with the most weight
+        let error = face_weights.iter()
+            .zip(non_face_weights.iter())
+            .fold(0.0, |acc, (&face_weight, &non_face_weight)| {
+                acc + f64::min(face_weight, non_face_weight)
+            });
+
+        if error < error_star {
+            error_star = error;
+            fi_star = Some(fi);
+            table_star = Some(face_weights.iter()
+                .zip(non_face_weights.iter())
+                .map(|(&face_weight, &non_face_weight)| {
+                    if face_weight >= non_face_weight { 1.0 } else { -1.0 }
+                })
+                .collect());
+        }
+    }
+
+    let feature = if reuse_features {
+        feature_collection[fi_star.unwrap()]
+    } else {
+        feature_collection.remove(fi_star.unwrap())
+    };
+
+    LbpClassifier::new(feature, table_star.unwrap())
+}
+
+pub fn grow_tree<R: Rng>(depth: usize,
+                         feature_collection: &mut Vec<HaarLikeFeature>,
+                         image_collection: &Vec<DataPoint>,
+                         weights: &Vector<f64>,
+                         reuse_features: bool,
+                         feature_fraction: f64,
+                         rng: &mut R)
+                         -> DecisionNode {
+    // weighted majority of the samples that reach this node
+    let balance = image_collection.iter()
+        .zip(weights.iter())
+        .fold(0.0, |acc, (data_point, weight)| acc + data_point.label * weight);
+
+    let is_pure = image_collection.iter()
+        .zip(weights.iter())
+        .all(|(data_point, &weight)| weight == 0.0 || data_point.label * balance > 0.0);
+
+    if depth == 0 || is_pure {
+        return DecisionNode::Leaf { label: if balance < 0.0 { -1.0 } else { 1.0 } };
+    }
+
+    let feature = weak_learner(feature_collection,
+                               image_collection,
+                               weights,
+                               reuse_features,
+                               feature_fraction,
+                               rng);
+
+    // samples that do not reach a branch get zero weight there
+    let predictions: Vec<_> = image_collection.iter()
+        .map(|data_point| feature.predict(&data_point.integral_image))
+        .collect();
+
+    let positive_weights: Vector<f64> = predictions.iter()
+        .zip(weights.iter())
+        .map(|(&prediction, &weight)| if prediction > 0.0 { weight } else { 0.0 })
+        .collect();
+
+    let negative_weights: Vector<f64> = predictions.iter()
+        .zip(weights.iter())
+        .map(|(&prediction, &weight)| if prediction > 0.0 { 0.0 } else { weight })
+        .collect();
+
+    let positive = grow_tree(depth - 1,
+                             feature_collection,
+                             image_collection,
+                             &positive_weights,
+                             reuse_features,
+                             feature_fraction,
+                             rng);
+
+    let negative = grow_tree(depth - 1,
+                             feature_collection,
+                             image_collection,
+                             &negative_weights,
+                             reuse_features,
+                             feature_fraction,
+                             rng);
+
+    DecisionNode::Split {
+        feature: feature,
+        positive: Box::new(positive),
+        negative: Box::new(negative),
+    }
+}
+
+// candidate features of one family; the weak learners take out the ones they
+// pick unless features are reused
+pub enum FeaturePool {
+    Haar(Vec<HaarLikeFeature>),
+    Lbp(Vec<LbpFeature>),
+}
+
+// panics on a sample boosting cannot learn from, rather than on the NaN
+// weights or scores it would lead to
+fn check_samples(image_collection: &Vec<DataPoint>, num_faces: usize, num_non_faces: usize) {
+    assert!(num_faces > 0 && num_non_faces > 0,
+            "Boosting needs both faces and non-faces, got {} and {}",
+            num_faces,
+            num_non_faces);
+
+    for (index, data_point) in image_collection.iter().enumerate() {
+        assert!(data_point.image_data.data().iter().all(|value| value.is_finite()),
+                "Sample {} has NaN or infinite pixels",
+                index);
+        assert!(data_point.label == 1.0 || data_point.label == -1.0,
+                "Sample {} is labelled {} instead of 1 or -1",
+                index,
+                data_point.label);
+    }
+}
+
+// the weights of the samples as logarithms, so that samples many rounds keep
+// getting right or wrong neither underflow nor overflow
+struct LogWeights {
+    log_weights: Vec<f64>,
+}
+
+impl LogWeights {
+    // each class starts with half of the weight
+    fn new(image_collection: &Vec<DataPoint>,
+           num_faces: usize,
+           num_non_faces: usize)
+           -> LogWeights {
+        let log_weights = image_collection.iter()
+            .map(|data_point| {
+                let class_size = if data_point.label > 0.0 { num_faces } else { num_non_faces };
+                -((2 * class_size) as f64).ln()
+            })
+            .collect();
+
+        LogWeights { log_weights: log_weights }
+    }
+
+    // multiplies the weight of sample i by exp(factor(i))
+    fn scale<F: Fn(usize) -> f64>(&mut self, factor: F) {
+        for (i, log_weight) in self.log_weights.iter_mut().enumerate() {
+            *log_weight += factor(i);
+        }
+    }
+
+    // weights summing up to 1, through the largest one so that exp stays finite
+    fn normalize(&mut self) -> Vector<f64> {
+        let max = self.log_weights.iter().fold(f64::NEG_INFINITY, |acc, &w| acc.max(w));
+        let sum = self.log_weights.iter().fold(0.0, |acc, &w| acc + (w - max).exp());
+        let log_sum = max + sum.ln();
+
+        assert!(log_sum.is_finite(), "Sample weights are no longer finite");
+
+        for log_weight in self.log_weights.iter_mut() {
+            *log_weight -= log_sum;
+        }
+
+        self.log_weights.iter().map(|&w| w.exp()).collect()
+    }
+}
+
+// asymboost: the cost `cost_ratio` of a missed face over a false detection is
+// spread evenly over the rounds. each round scales faces up and non-faces down
+// by the log returned here: after all of them faces weigh sqrt(k) more and
+// non-faces sqrt(k) less, so faces over non-faces is k times what symmetric
+// adaboost gives
+fn log_asymmetry(cost_ratio: f64, num_rounds: usize) -> f64 {
+    assert!(cost_ratio > 0.0, "The cost ratio has to be positive, got {}", cost_ratio);
+
+    cost_ratio.sqrt().ln() / (num_rounds as f64)
+}
+
+// a decision tree of haar features or an lbp table, fit to `weights`
+fn weak_classifier<R: Rng>(tree_depth: usize,
+                           feature_pool: &mut FeaturePool,
+                           image_collection: &Vec<DataPoint>,
+                           weights: &Vector<f64>,
+                           reuse_features: bool,
+                           feature_fraction: f64,
+                           rng: &mut R)
+                           -> WeakClassifier {
+    match *feature_pool {
+        FeaturePool::Haar(ref mut feature_collection) => {
+            let root = grow_tree(tree_depth,
+                                 feature_collection,
+                                 image_collection,
+                                 weights,
+                                 reuse_features,
+                                 feature_fraction,
+                                 rng);
+            WeakClassifier::Tree(DecisionTree::new(root))
+        }
+        FeaturePool::Lbp(ref mut feature_collection) => {
+            WeakClassifier::Lbp(lbp_weak_learner(feature_collection,
+                                                 image_collection,
+                                                 weights,
+                                                 reuse_features,
+                                                 feature_fraction,
+                                                 rng))
+        }
+    }
+}
+
+// +1 for the samples h gets right and -1 for the others
+fn agreements(h: &WeakClassifier, image_collection: &Vec<DataPoint>) -> Vec<f64> {
+    image_collection.iter()
+        .map(|data_point| {
+            let prediction = h.predict(&data_point.integral_image);
+            if data_point.label * prediction > 0.0 { 1.0 } else { -1.0 }
+        })
+        .collect()
+}
+
+fn weighted_error(agreements: &Vec<f64>, weights: &Vector<f64>) -> f64 {
+    agreements.iter()
+        .zip(weights.iter())
+        .fold(0.0, |acc, (&agreement, weight)| {
+            if agreement < 0.0 { acc + weight } else { acc }
+        })
+}
+
+// the weighted error of the weak classifier a search of the whole pool would
+// pick this round, to compare a sampled round with. it takes nothing out of
+// the pool and, as the whole pool is searched, draws nothing from `rng`
+fn full_search_error<R: Rng>(tree_depth: usize,
+                             feature_pool: &mut FeaturePool,
+                             image_collection: &Vec<DataPoint>,
+                             weights: &Vector<f64>,
+                             rng: &mut R)
+                             -> f64 {
+    let h = weak_classifier(tree_depth, feature_pool, image_collection, weights, true, 1.0, rng);
+
+    weighted_error(&agreements(&h, image_collection), weights)
+}
+
+// with `stop_when_separated`, stops early once the stage built so far
+// classifies every sample right: the sign of its summed votes is all a stage
+// of a cascade looks at. a soft cascade thresholds every partial sum instead,
+// so keeps all its rounds. each weak learner only looks at `feature_fraction`
+// of the pool, 1 searching all of it, drawn from `rng` so that a seeded one
+// trains the same classifiers every time
+pub fn adaboost<R: Rng>(num_rounds: usize,
+                        tree_depth: usize,
+                        cost_ratio: f64,
+                        reuse_features: bool,
+                        stop_when_separated: bool,
+                        feature_fraction: f64,
+                        feature_pool: &mut FeaturePool,
+                        image_collection: &Vec<DataPoint>,
+                        num_faces: usize,
+                        num_non_faces: usize,
+                        rng: &mut R)
+                        -> Vec<WeakClassifier> {
+    check_samples(image_collection, num_faces, num_non_faces);
+
+    let mut log_weights = LogWeights::new(image_collection, num_faces, num_non_faces);
+
+    let mut composition = Vec::new();
+
+    // vote of the stage so far for each sample
+    let mut stage_scores = vec![0.0; image_collection.len()];
+
+    let log_asymmetry = log_asymmetry(cost_ratio, num_rounds);
+
+    for t in 0..num_rounds {
+        println!("Begun round: {}", t + 1);
+
+        log_weights.scale(|i| image_collection[i].label * log_asymmetry);
+        let weights = log_weights.normalize();
+
+        // searched before h takes its feature out of the pool
+        let full_epsilon = if feature_fraction < 1.0 {
+            Some(full_search_error(tree_depth, feature_pool, image_collection, &weights, rng))
+        } else {
+            None
+        };
+
+        let mut h = weak_classifier(tree_depth,
+                                    feature_pool,
+                                    image_collection,
+                                    &weights,
+                                    reuse_features,
+                                    feature_fraction,
+                                    rng);
+
+        let agreements = agreements(&h, image_collection);
+        let epsilon = weighted_error(&agreements, &weights);
+        assert!(!epsilon.is_nan(), "Weighted error of h({}) is NaN", t + 1);
+
+        let epsilon = epsilon.max(MIN_ERROR).min(1.0 - MIN_ERROR);
+        let alpha = 0.5 * ((1.0 - epsilon) / epsilon).ln();
+
+        h.set_weight(alpha);
+
+        println!("h({}) = {:?}", t + 1, h);
+        match full_epsilon {
+            Some(full_epsilon) => {
+                println!("Weighted error of h({}): {:.6}, {:.6} searching the whole pool",
+                         t + 1,
+                         epsilon,
+                         full_epsilon.max(MIN_ERROR).min(1.0 - MIN_ERROR))
+            }
+            None => println!("Weighted error of h({}): {:.6}", t + 1, epsilon),
+        }
+
+        for (score, data_point) in stage_scores.iter_mut().zip(image_collection.iter()) {
+            *score += h.predict(&data_point.integral_image);
+        }
+        let separated = stage_scores.iter()
+            .zip(image_collection.iter())
+            .all(|(&score, data_point)| data_point.label * score > 0.0);
+
+        composition.push(h);
+
+        // the weights of the samples h gets right shrink by exp(-alpha) and
+        // the others grow by exp(alpha)
+        log_weights.scale(|i| -alpha * agreements[i]);
+
+        println!("Finished round: {}", t + 1);
+
+        if stop_when_separated && separated && t + 1 < num_rounds {
+            println!("Stopped after {} rounds: the stage separates every sample", t + 1);
+            break;
+        }
+    }
+
+    composition
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rulinalg::matrix::Matrix;
+    use integral_image::IntegralImage;
+    use synthetic::{seeded_rng, synthetic_dataset};
+    use shared::{ImageData, WINDOW_HEIGHT, WINDOW_WIDTH};
+
+    // faces bright on the left half and non-faces on the right half, which
+    // many features tell apart without a single mistake
+    fn halves(num_faces: usize, num_non_faces: usize) -> Vec<DataPoint> {
+        (0..num_faces + num_non_faces)
+            .map(|i| {
+                let is_face = i < num_faces;
+                let image_data: ImageData = Matrix::from_fn(WINDOW_HEIGHT, WINDOW_WIDTH, |x, _| {
+                    if (x < WINDOW_WIDTH / 2) == is_face { 1.0 } else { 0.0 }
+                });
+                let integral_image = IntegralImage::build(&image_data);
+
+                DataPoint {
+                    image_data: image_data,
+                    integral_image: integral_image,
+                    label: if is_face { 1.0 } else { -1.0 },
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn perfect_separation_stops_with_a_finite_weight() {
+        let image_collection = halves(4, 6);
+        let mut feature_pool = FeaturePool::Haar(HaarLikeFeature::generate_all_features());
+
+        let composition = adaboost(10,
+                                   1,
+                                   2.0,
+                                   false,
+                                   true,
+                                   1.0,
+                                   &mut feature_pool,
+                                   &image_collection,
+                                   4,
+                                   6,
+                                   &mut seeded_rng(0));
+
+        assert_eq!(composition.len(), 1);
+        assert!(composition[0].weight().is_finite() && composition[0].weight() > 0.0);
+        assert!(image_collection.iter().all(|data_point| {
+            data_point.label * composition[0].predict(&data_point.integral_image) > 0.0
+        }));
+    }
+
+    #[test]
+    #[should_panic(expected = "Sample 3 has NaN or infinite pixels")]
+    fn nan_pixels_are_reported() {
+        let mut image_collection = halves(2, 2);
+        image_collection[3].image_data[[5, 5]] = f64::NAN;
+        let mut feature_pool = FeaturePool::Haar(HaarLikeFeature::generate_all_features());
+
+        adaboost(1,
+                 1,
+                 1.0,
+                 false,
+                 true,
+                 1.0,
+                 &mut feature_pool,
+                 &image_collection,
+                 2,
+                 2,
+                 &mut seeded_rng(0));
+    }
+
+    // face weight over non-face weight after `num_rounds` rounds of asymboost
+    // with the same votes, alpha 0.5 each round
+    fn class_ratio(cost_ratio: f64, num_rounds: usize) -> f64 {
+        let image_collection = halves(4, 6);
+        let mut log_weights = LogWeights::new(&image_collection, 4, 6);
+        let log_asymmetry = log_asymmetry(cost_ratio, num_rounds);
+
+        for t in 0..num_rounds {
+            log_weights.scale(|i| image_collection[i].label * log_asymmetry);
+            log_weights.normalize();
+            // a different sample is wrong every round
+            log_weights.scale(|i| if i == t % 10 { 0.5 } else { -0.5 });
+        }
+
+        let weights = log_weights.normalize();
+        let faces = weights.iter().take(4).fold(0.0, |acc, w| acc + w);
+
+        faces / (1.0 - faces)
+    }
+
+    #[test]
+    fn faces_end_up_k_times_heavier_than_without_asymmetry() {
+        for &cost_ratio in &[0.5, 4.0, 10.0] {
+            for &num_rounds in &[1, 7] {
+                let ratio = class_ratio(cost_ratio, num_rounds) / class_ratio(1.0, num_rounds);
+                assert!((ratio - cost_ratio).abs() < 1e-9 * cost_ratio,
+                        "{} after {} rounds for k = {}",
+                        ratio,
+                        num_rounds,
+                        cost_ratio);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "The cost ratio has to be positive, got 0")]
+    fn a_cost_ratio_of_zero_is_reported() {
+        let image_collection = halves(2, 2);
+        let mut feature_pool = FeaturePool::Haar(HaarLikeFeature::generate_all_features());
+
+        adaboost(1,
+                 1,
+                 0.0,
+                 false,
+                 true,
+                 1.0,
+                 &mut feature_pool,
+                 &image_collection,
+                 2,
+                 2,
+                 &mut seeded_rng(0));
+    }
+
+    #[test]
+    fn sampled_features_are_drawn_from_the_rng() {
+        let a = sample_features(1000, 0.05, &mut seeded_rng(3));
+        let b = sample_features(1000, 0.05, &mut seeded_rng(3));
+
+        assert_eq!(a.len(), 50);
+        assert_eq!(a, b);
+        assert!(a.windows(2).all(|pair| pair[0] < pair[1]) && a[49] < 1000);
+        assert_ne!(a, sample_features(1000, 0.05, &mut seeded_rng(4)));
+
+        assert_eq!(sample_features(10, 1.0, &mut seeded_rng(3)), (0..10).collect::<Vec<_>>());
+        assert_eq!(sample_features(10, 0.01, &mut seeded_rng(3)).len(), 1);
+    }
+
+    #[test]
+    fn the_same_seed_picks_the_same_features() {
+        let (image_collection, num_faces, num_non_faces) = synthetic_dataset(0, 20, 20);
+        let train = |seed| {
+            let mut feature_pool = FeaturePool::Haar(HaarLikeFeature::generate_all_features());
+            let composition = adaboost(5,
+                                       1,
+                                       1.0,
+                                       false,
+                                       true,
+                                       0.02,
+                                       &mut feature_pool,
+                                       &image_collection,
+                                       num_faces,
+                                       num_non_faces,
+                                       &mut seeded_rng(seed));
+
+            composition.iter().map(|h| format!("{:?}", h)).collect::<Vec<_>>()
+        };
+
+        assert_eq!(train(5), train(5));
+        assert_ne!(train(5), train(6));
+    }
+
+    #[test]
+    fn the_full_search_keeps_the_pool_and_does_no_worse_than_a_sample() {
+        let (image_collection, _, _) = synthetic_dataset(0, 20, 20);
+        let weights = Vector::new(vec![1.0 / 40.0; 40]);
+        let mut feature_pool = FeaturePool::Haar(HaarLikeFeature::generate_all_features());
+        let pool_size = HaarLikeFeature::generate_all_features().len();
+
+        let full_error = full_search_error(1,
+                                           &mut feature_pool,
+                                           &image_collection,
+                                           &weights,
+                                           &mut seeded_rng(0));
+        match feature_pool {
+            FeaturePool::Haar(ref features) => assert_eq!(features.len(), pool_size),
+            FeaturePool::Lbp(_) => unreachable!(),
+        }
+
+        for seed in 0..5 {
+            let h = weak_classifier(1,
+                                    &mut feature_pool,
+                                    &image_collection,
+                                    &weights,
+                                    false,
+                                    0.02,
+                                    &mut seeded_rng(seed));
+            let sampled_error = weighted_error(&agreements(&h, &image_collection), &weights);
+
+            assert!(full_error <= sampled_error,
+                    "{} searching the whole pool, {} with seed {}",
+                    full_error,
+                    sampled_error,
+                    seed);
+        }
+    }
+
+    #[test]
+    fn the_stage_stops_once_its_votes_separate_every_sample() {
+        // two votes of +-alpha always side with the heavier one, so a stage
+        // separates what no single stump does after three rounds at the least
+        let (image_collection, num_faces, num_non_faces) = synthetic_dataset(3, 20, 20);
+        let mut feature_pool = FeaturePool::Haar(HaarLikeFeature::generate_all_features());
+
+        let composition = adaboost(30,
+                                   1,
+                                   1.0,
+                                   false,
+                                   true,
+                                   1.0,
+                                   &mut feature_pool,
+                                   &image_collection,
+                                   num_faces,
+                                   num_non_faces,
+                                   &mut seeded_rng(0));
+
+        let separates = |classifiers: &[WeakClassifier]| {
+            image_collection.iter().all(|data_point| {
+                let vote = classifiers.iter()
+                    .fold(0.0, |acc, h| acc + h.predict(&data_point.integral_image));
+                data_point.label * vote > 0.0
+            })
+        };
+
+        assert_eq!(composition.len(), 3);
+        assert!((0..3).all(|i| !separates(&composition[i..i + 1])));
+        assert!(!separates(&composition[..2]));
+        assert!(separates(&composition));
+    }
+}
@@ -0,0 +1,200 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_json;
+extern crate image;
+extern crate rulinalg;
+extern crate rand;
+
+mod load;
+mod pgm;
+mod manifest;
+mod synthetic;
+mod shared;
+mod geometry;
+mod integral_image;
+mod annotations;
+mod warp;
+mod args;
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use rand::{Rng, SeedableRng, StdRng};
+use annotations::{get_faces_map, FaceFeature, EYE_ROW};
+use geometry::Rect;
+use load::open_gray_image;
+use warp::extract_patch;
+use shared::{WINDOW_HEIGHT, WINDOW_WIDTH};
+
+// how many random windows are tried per negative kept
+const NEGATIVE_ATTEMPTS: usize = 20;
+
+// axis aligned box around the rotated face square
+fn face_bounds(feature: &FaceFeature) -> Rect {
+    let size = feature.face_size();
+    let (sin, cos) = feature.angle().sin_cos();
+    let eyes_center = feature.eyes_center();
+
+    let corners: Vec<(f64, f64)> = [(-0.5, -EYE_ROW), (0.5, -EYE_ROW), (-0.5, 1.0 - EYE_ROW),
+                                    (0.5, 1.0 - EYE_ROW)]
+        .iter()
+        .map(|&(u, v)| {
+            (eyes_center.0 + (u * cos - v * sin) * size,
+             eyes_center.1 + (u * sin + v * cos) * size)
+        })
+        .collect();
+
+    let min_x = corners.iter().fold(::std::f64::INFINITY, |acc, c| acc.min(c.0)).max(0.0);
+    let min_y = corners.iter().fold(::std::f64::INFINITY, |acc, c| acc.min(c.1)).max(0.0);
+    let max_x = corners.iter().fold(0.0, |acc: f64, c| acc.max(c.0));
+    let max_y = corners.iter().fold(0.0, |acc: f64, c| acc.max(c.1));
+
+    Rect::new(min_x as usize,
+              min_y as usize,
+              (max_x - min_x).ceil() as usize,
+              (max_y - min_y).ceil() as usize)
+}
+
+// up to `count` random square windows of a `width` x `height` image, touching
+// neither a face nor each other
+fn pick_negatives<R: Rng>(rng: &mut R,
+                          width: usize,
+                          height: usize,
+                          face_boxes: &Vec<Rect>,
+                          count: usize)
+                          -> Vec<Rect> {
+    let min_size = WINDOW_WIDTH.max(WINDOW_HEIGHT);
+    let max_size = width.min(height);
+    let mut windows: Vec<Rect> = Vec::new();
+
+    if max_size < min_size {
+        return windows;
+    }
+
+    for _ in 0..(count * NEGATIVE_ATTEMPTS) {
+        if windows.len() == count {
+            break;
+        }
+
+        let size = rng.gen_range(min_size, max_size + 1);
+        let window = Rect::new(rng.gen_range(0, width - size + 1),
+                               rng.gen_range(0, height - size + 1),
+                               size,
+                               size);
+
+        if face_boxes.iter().chain(windows.iter()).any(|other| other.intersects(&window)) {
+            continue;
+        }
+
+        windows.push(window);
+    }
+
+    windows
+}
+
+fn main() {
+    let annotations = args::get_option("annotations")
+        .unwrap_or("./data/validation/faces.txt".to_string());
+    let images = args::get_option("images").unwrap_or("./data/validation/pics".to_string());
+    let output = args::get_option("output").unwrap_or("./data/built".to_string());
+    let split = args::get_option("split").unwrap_or("train".to_string());
+    let negatives_per_image = args::get_option("negatives").unwrap_or(10);
+    let seed: usize = args::get_option("seed").unwrap_or(0);
+
+    fs::create_dir_all(format!("{}/faces", output)).expect("Unable to create output directory");
+    fs::create_dir_all(format!("{}/non-faces", output))
+        .expect("Unable to create output directory");
+
+    let mut rng: StdRng = SeedableRng::from_seed(&[seed][..]);
+    let mut manifest = vec!["path,label,x,y,width,height,split".to_string()];
+
+    let mut faces_map: Vec<_> = get_faces_map(&annotations).into_iter().collect();
+    faces_map.sort_by(|a, b| a.0.cmp(&b.0));
+
+    for (filename, features) in faces_map.iter() {
+        let image_path = Path::new(&images).join(filename);
+        if !image_path.exists() {
+            println!("Skipping missing image: {}", image_path.display());
+            continue;
+        }
+
+        let img = open_gray_image(&image_path);
+        let (width, height) = img.dimensions();
+        let stem = Path::new(filename).file_stem().unwrap().to_string_lossy().into_owned();
+
+        // faces, with the eyes levelled
+        for (i, feature) in features.iter().enumerate() {
+            let face = extract_patch(&img,
+                                     feature.eyes_center(),
+                                     (0.5, EYE_ROW),
+                                     feature.face_size(),
+                                     feature.angle(),
+                                     WINDOW_WIDTH as u32,
+                                     WINDOW_HEIGHT as u32);
+
+            let path = format!("faces/{}_{}.png", stem, i);
+            face.save(format!("{}/{}", output, path)).expect("Unable to write image");
+            manifest.push(format!("{},1,,,,,{}", path, split));
+        }
+
+        // random windows that do not touch any face or each other
+        let face_boxes: Vec<_> = features.iter().map(face_bounds).collect();
+        let negatives = pick_negatives(&mut rng,
+                                       width as usize,
+                                       height as usize,
+                                       &face_boxes,
+                                       negatives_per_image);
+
+        for (i, window) in negatives.iter().enumerate() {
+            let size = window.width;
+            let center = (window.x as f64 + size as f64 / 2.0,
+                          window.y as f64 + size as f64 / 2.0);
+            let non_face = extract_patch(&img,
+                                         center,
+                                         (0.5, 0.5),
+                                         size as f64,
+                                         0.0,
+                                         WINDOW_WIDTH as u32,
+                                         WINDOW_HEIGHT as u32);
+
+            let path = format!("non-faces/{}_{}.png", stem, i);
+            non_face.save(format!("{}/{}", output, path)).expect("Unable to write image");
+            manifest.push(format!("{},-1,,,,,{}", path, split));
+        }
+
+        println!("{}: {} faces, {} non-faces", filename, features.len(), negatives.len());
+    }
+
+    let mut f = File::create(format!("{}/manifest.csv", output)).expect("Unable to create file");
+    f.write_all((manifest.join("\n") + "\n").as_bytes()).expect("Unable to write data");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use synthetic::seeded_rng;
+
+    #[test]
+    fn negatives_touch_neither_faces_nor_each_other() {
+        let face_boxes = vec![Rect::new(40, 30, 50, 50), Rect::new(150, 100, 30, 40)];
+
+        for seed in 0..5 {
+            let negatives = pick_negatives(&mut seeded_rng(seed), 240, 180, &face_boxes, 10);
+            assert!(!negatives.is_empty());
+
+            for (i, window) in negatives.iter().enumerate() {
+                assert!(window.right() <= 240 && window.bottom() <= 180);
+                assert!(window.width >= WINDOW_WIDTH && window.width == window.height);
+                assert!(face_boxes.iter().all(|face_box| !face_box.intersects(window)));
+                assert!(negatives[i + 1..].iter().all(|other| !other.intersects(window)),
+                        "seed {}: {:?} overlaps another negative",
+                        seed,
+                        window);
+            }
+        }
+
+        // no room for a single window
+        assert!(pick_negatives(&mut seeded_rng(0), 18, 100, &Vec::new(), 10).is_empty());
+    }
+}
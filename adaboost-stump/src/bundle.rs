@@ -0,0 +1,168 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use image::GrayImage;
+use image::imageops::flip_horizontal;
+use serde_json;
+use compiled_cascade::WindowLayout;
+use detection_params::DetectionParams;
+use detector::Detector;
+use geometry::Rect;
+use model::Model;
+use scanner::{Detection, group_detections, merge_labels, scan_compiled_all, scan_rotations,
+              scan_windows};
+
+// one line of the bundle file; relative model paths start at the bundle file
+#[derive(Serialize, Deserialize, Debug)]
+struct CascadeSpec {
+    label: String,
+    model: String,
+    #[serde(default)]
+    mirror: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct BundleSpec {
+    cascades: Vec<CascadeSpec>,
+}
+
+#[derive(Debug)]
+pub struct BundleEntry {
+    pub label: String,
+    pub model: Model,
+    // the model is run on the mirror image
+    pub mirror: bool,
+}
+
+// several detectors run over the same picture, e.g. a frontal cascade and a
+// profile one looking both ways
+#[derive(Debug)]
+pub struct DetectorBundle {
+    entries: Vec<BundleEntry>,
+}
+
+impl DetectorBundle {
+    pub fn new(entries: Vec<BundleEntry>) -> DetectorBundle {
+        DetectorBundle { entries: entries }
+    }
+
+    pub fn load(path: &str) -> DetectorBundle {
+        let mut bundle_raw = String::new();
+        let mut f = File::open(path).expect("Unable to open file");
+        f.read_to_string(&mut bundle_raw).expect("Unable to read string");
+
+        let spec: BundleSpec = serde_json::from_str(&bundle_raw).unwrap();
+        let directory = Path::new(path).parent().unwrap_or(Path::new("."));
+
+        let entries = spec.cascades
+            .into_iter()
+            .map(|cascade| {
+                let model_path = directory.join(&cascade.model);
+
+                BundleEntry {
+                    label: cascade.label,
+                    model: Model::load(&model_path.to_string_lossy()),
+                    mirror: cascade.mirror,
+                }
+            })
+            .collect();
+
+        DetectorBundle::new(entries)
+    }
+
+    pub fn entries(&self) -> &Vec<BundleEntry> {
+        &self.entries
+    }
+
+    // accepted windows of every cascade, labelled, one list per cascade. at
+    // each window size the compiled cascades share one pass over one integral
+    // image; the others go window by window
+    pub fn scan(&self,
+                data: &GrayImage,
+                candidates: Option<&GrayImage>,
+                params: &DetectionParams)
+                -> Vec<Vec<Detection>> {
+        let num_entries = self.entries.len();
+
+        let colls = scan_rotations(data, candidates, params, num_entries, |image, mask, levels| {
+            let mut colls = vec![Vec::new(); num_entries];
+            let mut mirror_image = None;
+            let mut mirror_mask = None;
+
+            for level in levels.iter() {
+                let layout = WindowLayout::new(image.width() as usize + 1, level.scale);
+
+                let compiled: Vec<_> = self.entries
+                    .iter()
+                    .map(|entry| {
+                        entry.model.compile(&WindowLayout { mirror: entry.mirror, ..layout })
+                    })
+                    .collect();
+
+                let mut compiled_detections = {
+                    let cascades = compiled.iter().filter_map(|cascade| cascade.as_ref()).collect();
+                    scan_compiled_all(&cascades, image, level, mask).into_iter()
+                };
+
+                for ((entry, cascade), coll) in
+                    self.entries.iter().zip(compiled.iter()).zip(colls.iter_mut()) {
+                    coll.extend(if cascade.is_some() {
+                        compiled_detections.next().unwrap()
+                    } else if entry.mirror {
+                        // scans the flipped picture and flips the boxes back
+                        let flipped = mirror_image.get_or_insert_with(|| flip_horizontal(image));
+                        let flipped_mask = mirror_mask.get_or_insert_with(|| {
+                            mask.map(|mask| mask.mirrored())
+                        });
+
+                        scan_windows(&entry.model, flipped, level, flipped_mask.as_ref())
+                            .into_iter()
+                            .map(|mut detection| {
+                                let rect = detection.rect;
+                                detection.rect = Rect::new(image.width() as usize - rect.right(),
+                                                           rect.y,
+                                                           rect.width,
+                                                           rect.height);
+                                detection
+                            })
+                            .collect()
+                    } else {
+                        scan_windows(&entry.model, image, level, mask)
+                    });
+                }
+            }
+
+            colls
+        });
+
+        self.entries
+            .iter()
+            .zip(colls.into_iter())
+            .map(|(entry, detections)| {
+                detections.into_iter()
+                    .map(|mut detection| {
+                        detection.label = Some(entry.label.clone());
+                        detection
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // groups the windows of each cascade on their own, then merges the
+    // overlapping groups of different cascades
+    pub fn detect(&self,
+                  data: &GrayImage,
+                  candidates: Option<&GrayImage>,
+                  params: &DetectionParams,
+                  min_overlap: f64,
+                  min_neighbours: usize)
+                  -> Vec<Detection> {
+        let grouped = self.scan(data, candidates, params)
+            .iter()
+            .flat_map(|windows| group_detections(windows, min_overlap, min_neighbours).into_iter())
+            .collect();
+
+        params.keep_best(merge_labels(&grouped, min_overlap))
+    }
+}
@@ -0,0 +1,209 @@
+// C interface of the detector. every function catches panics and reports
+// failures through its return value and adaboost_last_error
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+use image::GrayImage;
+use model::Model;
+use detection_params::DetectionParams;
+use scanner::{Detection, group_detections, scan};
+
+/// bumped whenever a signature or struct of the header changes
+pub const ADABOOST_ABI_VERSION: u32 = 1;
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::new("").unwrap());
+}
+
+fn set_last_error(message: &str) {
+    let message = CString::new(message.replace('\0', " ")).unwrap();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = message);
+}
+
+// runs `f`, turning an error or a panic into `failure` and the last error
+fn guard<T, F: FnOnce() -> Result<T, String>>(failure: T, f: F) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => value,
+        Ok(Err(message)) => {
+            set_last_error(&message);
+            failure
+        }
+        Err(_) => {
+            set_last_error("Internal error");
+            failure
+        }
+    }
+}
+
+/// a loaded model
+pub struct AdaboostDetector {
+    model: Model,
+}
+
+/// the grouped detections of one image
+pub struct AdaboostDetections {
+    detections: Vec<Detection>,
+}
+
+/// one grouped detection, in pixels of the image passed in
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct AdaboostDetection {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub neighbours: u32,
+    pub score: f64,
+}
+
+/// ADABOOST_ABI_VERSION of the library, to compare with that of the header
+#[no_mangle]
+pub extern "C" fn adaboost_abi_version() -> u32 {
+    ADABOOST_ABI_VERSION
+}
+
+/// message of the last failure on this thread, valid until the next failure
+#[no_mangle]
+pub extern "C" fn adaboost_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| last_error.borrow().as_ptr())
+}
+
+/// null if the model cannot be read
+///
+/// # Safety
+///
+/// `model_path` is null or a nul-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn adaboost_detector_load(model_path: *const c_char)
+                                                -> *mut AdaboostDetector {
+    guard(ptr::null_mut(), || {
+        if model_path.is_null() {
+            return Err("Null model path".to_string());
+        }
+
+        let model_path = CStr::from_ptr(model_path)
+            .to_str()
+            .map_err(|_| "Model path is not valid UTF-8".to_string())?;
+
+        let model = Model::try_load(model_path)?;
+
+        Ok(Box::into_raw(Box::new(AdaboostDetector { model: model })))
+    })
+}
+
+/// # Safety
+///
+/// `detector` is null or came from adaboost_detector_load and was not freed yet
+#[no_mangle]
+pub unsafe extern "C" fn adaboost_detector_free(detector: *mut AdaboostDetector) {
+    if !detector.is_null() {
+        drop(Box::from_raw(detector));
+    }
+}
+
+/// scans `height` rows of `width` 8-bit pixels, rows starting `stride` bytes
+/// apart, and groups the windows. null on invalid arguments
+///
+/// # Safety
+///
+/// `detector` is null or a live detector, `pixels` is null or points to
+/// `height` rows of `stride` bytes, the last of them at least `width` long
+#[no_mangle]
+pub unsafe extern "C" fn adaboost_detect(detector: *const AdaboostDetector,
+                                         pixels: *const u8,
+                                         width: u32,
+                                         height: u32,
+                                         stride: usize,
+                                         min_neighbours: u32)
+                                         -> *mut AdaboostDetections {
+    guard(ptr::null_mut(), || {
+        if detector.is_null() || pixels.is_null() {
+            return Err("Null detector or pixels".to_string());
+        }
+        if width == 0 || height == 0 || stride < width as usize {
+            return Err(format!("Invalid image: {}x{} with stride {}", width, height, stride));
+        }
+
+        let detector = &*detector;
+        let (row_length, num_rows) = (width as usize, height as usize);
+        let buffer = slice::from_raw_parts(pixels, stride * (num_rows - 1) + row_length);
+
+        let mut data = Vec::with_capacity(row_length * num_rows);
+        for row in 0..num_rows {
+            data.extend_from_slice(&buffer[row * stride..row * stride + row_length]);
+        }
+
+        let data = GrayImage::from_raw(width, height, data).unwrap();
+        let windows = scan(&detector.model, &data, None, &DetectionParams::default());
+        let detections = group_detections(&windows, 0.3, min_neighbours as usize);
+
+        Ok(Box::into_raw(Box::new(AdaboostDetections { detections: detections })))
+    })
+}
+
+/// # Safety
+///
+/// `detections` is null or a live list from adaboost_detect
+#[no_mangle]
+pub unsafe extern "C" fn adaboost_detections_count(detections: *const AdaboostDetections)
+                                                   -> usize {
+    if detections.is_null() {
+        return 0;
+    }
+
+    let detections = &*detections;
+    detections.detections.len()
+}
+
+/// copies the detection at `index` into `out`: 0 on success, -1 out of range
+///
+/// # Safety
+///
+/// `detections` is null or a live list from adaboost_detect, `out` is null or
+/// points to writable memory for one AdaboostDetection
+#[no_mangle]
+pub unsafe extern "C" fn adaboost_detections_get(detections: *const AdaboostDetections,
+                                                 index: usize,
+                                                 out: *mut AdaboostDetection)
+                                                 -> c_int {
+    if detections.is_null() || out.is_null() {
+        set_last_error("Null detections or output");
+        return -1;
+    }
+
+    let detections = &*detections;
+    match detections.detections.get(index) {
+        Some(detection) => {
+            let rect = detection.rect;
+
+            *out = AdaboostDetection {
+                x: rect.x as u32,
+                y: rect.y as u32,
+                width: rect.width as u32,
+                height: rect.height as u32,
+                neighbours: detection.neighbours as u32,
+                score: detection.score,
+            };
+
+            0
+        }
+        None => {
+            set_last_error("Detection index out of range");
+            -1
+        }
+    }
+}
+
+/// # Safety
+///
+/// `detections` is null or came from adaboost_detect and was not freed yet
+#[no_mangle]
+pub unsafe extern "C" fn adaboost_detections_free(detections: *mut AdaboostDetections) {
+    if !detections.is_null() {
+        drop(Box::from_raw(detections));
+    }
+}
@@ -0,0 +1,309 @@
+use fast_integral_image::{FastIntegralImage, RectOffsets};
+use geometry::Rect;
+use haar_like_feature::HaarLikeFeature;
+use decision_tree::{DecisionNode, DecisionTree};
+use lbp_feature::LbpClassifier;
+use weak_classifier::WeakClassifier;
+use shared::WINDOW_WIDTH;
+
+// how the 19x19 window of the model is laid over the integral image: the
+// stride of its rows, the size of the window and whether the model looks at
+// the mirror image, which turns a left profile detector into a right one
+#[derive(Debug, Copy, Clone)]
+pub struct WindowLayout {
+    pub stride: usize,
+    pub scale: f64,
+    pub mirror: bool,
+}
+
+impl WindowLayout {
+    pub fn new(stride: usize, scale: f64) -> WindowLayout {
+        WindowLayout {
+            stride: stride,
+            scale: scale,
+            mirror: false,
+        }
+    }
+}
+
+// rectangle of a feature scaled to the window size, with the factor that
+// brings its sum back to the area it had in the 19x19 window
+#[derive(Debug, Copy, Clone)]
+struct CompiledBox {
+    offsets: RectOffsets,
+    weight: f64,
+}
+
+impl CompiledBox {
+    fn new(rect: &Rect, sign: f64, layout: &WindowLayout) -> CompiledBox {
+        let rect = if layout.mirror {
+            Rect::new(WINDOW_WIDTH - rect.right(), rect.y, rect.width, rect.height)
+        } else {
+            *rect
+        };
+
+        // edges are rounded rather than sizes, so that adjacent boxes stay adjacent
+        let scale = layout.scale;
+        let x = (rect.x as f64 * scale).round() as usize;
+        let y = (rect.y as f64 * scale).round() as usize;
+        let right = ((rect.right() as f64 * scale).round() as usize).max(x + 1);
+        let bottom = ((rect.bottom() as f64 * scale).round() as usize).max(y + 1);
+        let scaled = Rect::new(x, y, right - x, bottom - y);
+
+        CompiledBox {
+            offsets: RectOffsets::new(&scaled, layout.stride),
+            weight: sign * (rect.area() as f64) / (scaled.area() as f64),
+        }
+    }
+
+    fn sum(&self, integral_image: &FastIntegralImage, origin: usize) -> f64 {
+        self.weight * (integral_image.sum(origin, &self.offsets) as f64)
+    }
+}
+
+// windows are normalized by their brightest pixel before training, so the
+// threshold is compared against the raw score scaled by that pixel instead
+#[derive(Debug)]
+struct CompiledHaar {
+    boxes: Vec<CompiledBox>,
+    threshold: f64,
+    // weight times polarity
+    vote: f64,
+}
+
+impl CompiledHaar {
+    fn new(feature: &HaarLikeFeature, layout: &WindowLayout) -> CompiledHaar {
+        CompiledHaar {
+            boxes: feature.boxes()
+                .iter()
+                .map(|&(rect, sign)| CompiledBox::new(&rect, sign, layout))
+                .collect(),
+            threshold: feature.threshold,
+            vote: feature.weight * feature.polarity,
+        }
+    }
+
+    fn predict(&self, integral_image: &FastIntegralImage, origin: usize, max: f64) -> f64 {
+        let score = self.boxes.iter().fold(0.0, |acc, b| acc + b.sum(integral_image, origin));
+        self.vote * (score - self.threshold * max).signum()
+    }
+}
+
+#[derive(Debug)]
+enum CompiledNode {
+    Split {
+        feature: CompiledHaar,
+        positive: usize,
+        negative: usize,
+    },
+    Leaf { label: f64 },
+}
+
+// pushes the node and its children depth-first and returns its index
+fn compile_node(node: &DecisionNode,
+                layout: &WindowLayout,
+                nodes: &mut Vec<CompiledNode>)
+                -> usize {
+    let index = nodes.len();
+
+    match *node {
+        DecisionNode::Split { ref feature, ref positive, ref negative } => {
+            nodes.push(CompiledNode::Leaf { label: 0.0 });
+            let positive = compile_node(positive, layout, nodes);
+            let negative = compile_node(negative, layout, nodes);
+
+            nodes[index] = CompiledNode::Split {
+                feature: CompiledHaar::new(feature, layout),
+                positive: positive,
+                negative: negative,
+            };
+        }
+        DecisionNode::Leaf { label } => nodes.push(CompiledNode::Leaf { label: label }),
+    }
+
+    index
+}
+
+#[derive(Debug)]
+enum CompiledWeak {
+    // nodes of the tree in one vector, the root first
+    Tree { weight: f64, nodes: Vec<CompiledNode> },
+    // the eight neighbour blocks in code order, then the centre block
+    Lbp {
+        weight: f64,
+        blocks: Vec<CompiledBox>,
+        table: Vec<f64>,
+    },
+}
+
+impl CompiledWeak {
+    fn new(classifier: &WeakClassifier, layout: &WindowLayout) -> CompiledWeak {
+        match *classifier {
+            WeakClassifier::Tree(ref tree) => CompiledWeak::compile_tree(tree, layout),
+            WeakClassifier::Lbp(ref lbp) => CompiledWeak::compile_lbp(lbp, layout),
+        }
+    }
+
+    fn compile_tree(tree: &DecisionTree, layout: &WindowLayout) -> CompiledWeak {
+        let mut nodes = Vec::new();
+        compile_node(tree.root(), layout, &mut nodes);
+
+        CompiledWeak::Tree {
+            weight: tree.weight,
+            nodes: nodes,
+        }
+    }
+
+    fn compile_lbp(lbp: &LbpClassifier, layout: &WindowLayout) -> CompiledWeak {
+        CompiledWeak::Lbp {
+            weight: lbp.weight,
+            blocks: lbp.feature()
+                .rectangles()
+                .iter()
+                .map(|&(rect, _)| CompiledBox::new(&rect, 1.0, layout))
+                .collect(),
+            table: lbp.table().clone(),
+        }
+    }
+
+    fn predict(&self, integral_image: &FastIntegralImage, origin: usize, max: f64) -> f64 {
+        match *self {
+            CompiledWeak::Tree { weight, ref nodes } => {
+                let mut index = 0;
+
+                loop {
+                    match nodes[index] {
+                        CompiledNode::Split { ref feature, positive, negative } => {
+                            index = if feature.predict(integral_image, origin, max) > 0.0 {
+                                positive
+                            } else {
+                                negative
+                            };
+                        }
+                        CompiledNode::Leaf { label } => return weight * label,
+                    }
+                }
+            }
+            CompiledWeak::Lbp { weight, ref blocks, ref table } => {
+                let center = blocks[8].sum(integral_image, origin);
+
+                let code = blocks[..8].iter().enumerate().fold(0, |code, (bit, block)| {
+                    if block.sum(integral_image, origin) >= center {
+                        code | (1 << bit)
+                    } else {
+                        code
+                    }
+                });
+
+                weight * table[code]
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+enum CompiledKind {
+    Stages(Vec<Vec<CompiledWeak>>),
+    Soft {
+        classifiers: Vec<CompiledWeak>,
+        rejection_thresholds: Vec<f64>,
+    },
+}
+
+// a trained cascade with every rectangle turned into offsets for one window
+// layout, evaluated straight on a FastIntegralImage
+#[derive(Debug)]
+pub struct CompiledCascade {
+    kind: CompiledKind,
+}
+
+impl CompiledCascade {
+    pub fn from_stages(stages: &Vec<Vec<WeakClassifier>>,
+                       layout: &WindowLayout)
+                       -> CompiledCascade {
+        let stages = stages.iter()
+            .map(|stage| stage.iter().map(|h| CompiledWeak::new(h, layout)).collect())
+            .collect();
+
+        CompiledCascade { kind: CompiledKind::Stages(stages) }
+    }
+
+    pub fn from_soft_cascade(classifiers: &Vec<WeakClassifier>,
+                             rejection_thresholds: &Vec<f64>,
+                             layout: &WindowLayout)
+                             -> CompiledCascade {
+        CompiledCascade {
+            kind: CompiledKind::Soft {
+                classifiers: classifiers.iter()
+                    .map(|h| CompiledWeak::new(h, layout))
+                    .collect(),
+                rejection_thresholds: rejection_thresholds.clone(),
+            },
+        }
+    }
+
+    // stages of the cascade; every weak classifier of a soft cascade is a stage
+    pub fn num_stages(&self) -> usize {
+        match self.kind {
+            CompiledKind::Stages(ref stages) => stages.len(),
+            CompiledKind::Soft { ref classifiers, .. } => classifiers.len(),
+        }
+    }
+
+    // how many stages the window passes, and its margin at the last stage it
+    // reached: the vote of that stage, or for a soft cascade the score minus
+    // the rejection threshold it was compared with
+    pub fn evaluate(&self,
+                    integral_image: &FastIntegralImage,
+                    origin: usize,
+                    max: f64)
+                    -> (usize, f64) {
+        match self.kind {
+            CompiledKind::Stages(ref stages) => {
+                let mut vote = 0.0;
+
+                for (i, stage) in stages.iter().enumerate() {
+                    vote = stage.iter()
+                        .fold(0.0, |acc, h| acc + h.predict(integral_image, origin, max));
+
+                    if vote.signum() < 0.0 {
+                        return (i, vote);
+                    }
+                }
+
+                (stages.len(), vote)
+            }
+            CompiledKind::Soft { ref classifiers, ref rejection_thresholds } => {
+                let mut score = 0.0;
+
+                for (i, (h, &threshold)) in
+                    classifiers.iter().zip(rejection_thresholds.iter()).enumerate() {
+                    score += h.predict(integral_image, origin, max);
+
+                    if score < threshold {
+                        return (i, score - threshold);
+                    }
+                }
+
+                (classifiers.len(),
+                 score - rejection_thresholds.last().map_or(0.0, |&threshold| threshold))
+            }
+        }
+    }
+
+    // `max` is the brightest pixel of the window, at least 1. the score is the
+    // same as the one the uncompiled cascade gives
+    pub fn score(&self, integral_image: &FastIntegralImage, origin: usize, max: f64) -> Option<f64> {
+        let (passed, margin) = self.evaluate(integral_image, origin, max);
+
+        if passed == self.num_stages() {
+            Some(margin)
+        } else {
+            None
+        }
+    }
+
+    pub fn predict(&self, integral_image: &FastIntegralImage, origin: usize, max: f64) -> bool {
+        self.score(integral_image, origin, max).is_some()
+    }
+}
@@ -0,0 +1,102 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_json;
+extern crate image;
+extern crate rulinalg;
+extern crate rand;
+
+mod load;
+mod pgm;
+mod manifest;
+mod synthetic;
+mod shared;
+mod geometry;
+mod integral_image;
+mod haar_like_feature;
+mod decision_tree;
+mod lbp_feature;
+mod weak_classifier;
+mod prediction_ensemble;
+mod soft_cascade;
+mod detector;
+mod fast_integral_image;
+mod compiled_cascade;
+mod linear_svm;
+mod hog;
+mod model;
+mod evaluation;
+mod compression;
+mod args;
+
+use load::{get_data_from_args, get_split_from_args};
+use model::Model;
+use weak_classifier::WeakClassifier;
+use evaluation::{evaluate, Evaluation};
+use compression::compress_model;
+
+// weak classifiers of the model and the features they look at
+fn size(model: &Model) -> (usize, usize) {
+    let classifiers: Vec<&WeakClassifier> = match *model {
+        Model::Cascade(ref ensemble) => ensemble.stages().iter().flat_map(|stage| stage).collect(),
+        Model::SoftCascade(ref cascade) => cascade.classifiers().iter().collect(),
+        Model::Hog(_) => Vec::new(),
+    };
+
+    let num_features = classifiers.iter().fold(0, |acc, h| acc + h.rectangles().len());
+
+    (classifiers.len(), num_features)
+}
+
+fn print_evaluation(name: &str, model: &Model, evaluation: &Evaluation) {
+    let (num_classifiers, num_features) = size(model);
+
+    println!("{}: {} weak classifiers, {} features, detection rate {:.4}, \
+              false positive rate {:.4}",
+             name,
+             num_classifiers,
+             num_features,
+             evaluation.detection_rate(),
+             evaluation.false_positive_rate());
+}
+
+fn main() {
+    let model_path = args::get_option("model").unwrap_or("./foo.json".to_string());
+    let output = args::get_option("output").unwrap_or("compressed.json".to_string());
+
+    // share of the windows two thresholds of the same feature may split differently
+    let max_disagreement = args::get_option("max-disagreement").unwrap_or(0.01);
+    // share of the total weight of its stage a weak classifier needs to be kept
+    let min_alpha = args::get_option("min-alpha").unwrap_or(0.005);
+    let detection_rate = args::get_option("detection-rate").unwrap_or(0.99);
+
+    // the windows the rejection thresholds are calibrated on are not those the
+    // compression is judged on
+    let (image_collection, _, _) = get_data_from_args("./data/trainset", "train");
+    let eval_split = args::get_option("eval-split").unwrap_or("validation".to_string());
+    let eval_dataset = args::get_option("eval-dataset").unwrap_or("./data/testset".to_string());
+    let (held_out, num_faces, num_non_faces) = get_split_from_args(&eval_dataset, &eval_split);
+    assert!(num_faces > 0 && num_non_faces > 0,
+            "The {} split needs faces and non-faces to evaluate on",
+            eval_split);
+
+    let model = Model::load(&model_path);
+    let before = evaluate(&model, &held_out);
+    print_evaluation(&format!("Before, on {}", eval_split), &model, &before);
+
+    let (model, report) =
+        compress_model(model, &image_collection, max_disagreement, min_alpha, detection_rate);
+
+    let after = evaluate(&model, &held_out);
+    print_evaluation(&format!("After, on {}", eval_split), &model, &after);
+
+    println!("Merged {} weak classifiers ({} cancelled out), dropped {}",
+             report.merged,
+             report.cancelled,
+             report.dropped);
+    println!("Detection rate {:+.4}, false positive rate {:+.4}",
+             after.detection_rate() - before.detection_rate(),
+             after.false_positive_rate() - before.false_positive_rate());
+
+    model.save(&output);
+}
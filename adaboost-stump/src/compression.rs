@@ -0,0 +1,447 @@
+use haar_like_feature::HaarLikeFeature;
+use lbp_feature::LbpClassifier;
+use decision_tree::{DecisionNode, DecisionTree};
+use weak_classifier::WeakClassifier;
+use prediction_ensemble::PredictionEnsemble;
+use soft_cascade::SoftCascade;
+use model::Model;
+use shared::DataPoint;
+
+#[derive(Debug, Default)]
+pub struct CompressionReport {
+    // classifiers folded into an earlier one of their stage
+    pub merged: usize,
+    // merges whose votes cancelled out, leaving nothing
+    pub cancelled: usize,
+    // classifiers whose weight was too small to keep
+    pub dropped: usize,
+}
+
+fn leaf_label(node: &DecisionNode) -> Option<f64> {
+    match *node {
+        DecisionNode::Leaf { label } => Some(label),
+        DecisionNode::Split { .. } => None,
+    }
+}
+
+// a decision tree of depth 1 as its votes, weight included, for the windows
+// scoring at least the threshold and for the others
+struct Stump {
+    feature: HaarLikeFeature,
+    above: f64,
+    below: f64,
+}
+
+impl Stump {
+    fn from_classifier(classifier: &WeakClassifier) -> Option<Stump> {
+        let tree = match *classifier {
+            WeakClassifier::Tree(ref tree) => tree,
+            WeakClassifier::Lbp(_) => return None,
+        };
+
+        match *tree.root() {
+            DecisionNode::Split { ref feature, ref positive, ref negative } => {
+                let (positive, negative) = (leaf_label(positive)?, leaf_label(negative)?);
+
+                // the positive branch is taken above the threshold for a
+                // positive polarity and below it otherwise
+                let (above, below) = if feature.polarity > 0.0 {
+                    (positive, negative)
+                } else {
+                    (negative, positive)
+                };
+
+                Some(Stump {
+                    feature: feature.clone(),
+                    above: tree.weight * above,
+                    below: tree.weight * below,
+                })
+            }
+            DecisionNode::Leaf { .. } => None,
+        }
+    }
+
+    // share of the windows the two thresholds put on different sides
+    fn disagreement(&self, other: &Stump, image_collection: &Vec<DataPoint>) -> f64 {
+        let low = self.feature.threshold.min(other.feature.threshold);
+        let high = self.feature.threshold.max(other.feature.threshold);
+
+        let between = image_collection.iter()
+            .filter(|data_point| {
+                let score = self.feature.get_score(&data_point.integral_image);
+                score >= low && score < high
+            })
+            .count();
+
+        between as f64 / (image_collection.len().max(1) as f64)
+    }
+
+    // adds up the votes at the mean of the thresholds weighted by the votes
+    fn merge(self, other: Stump) -> Stump {
+        let weight = self.above.abs().max(self.below.abs());
+        let other_weight = other.above.abs().max(other.below.abs());
+
+        let mut feature = self.feature;
+        feature.threshold = (feature.threshold * weight +
+                             other.feature.threshold * other_weight) /
+                            (weight + other_weight);
+
+        Stump {
+            feature: feature,
+            above: self.above + other.above,
+            below: self.below + other.below,
+        }
+    }
+
+    // None when the votes cancelled out
+    fn into_classifier(self) -> Option<WeakClassifier> {
+        let weight = self.above.abs().max(self.below.abs());
+        if weight == 0.0 {
+            return None;
+        }
+
+        let (positive, negative) = if self.feature.polarity > 0.0 {
+            (self.above, self.below)
+        } else {
+            (self.below, self.above)
+        };
+
+        let mut tree = DecisionTree::new(DecisionNode::Split {
+            feature: self.feature,
+            positive: Box::new(DecisionNode::Leaf { label: positive / weight }),
+            negative: Box::new(DecisionNode::Leaf { label: negative / weight }),
+        });
+        tree.weight = weight;
+
+        Some(WeakClassifier::Tree(tree))
+    }
+}
+
+// two lbp classifiers on the same blocks add up code by code exactly
+fn merge_lbp(a: &LbpClassifier, b: &LbpClassifier) -> Option<WeakClassifier> {
+    let votes: Vec<f64> = a.table()
+        .iter()
+        .zip(b.table().iter())
+        .map(|(&vote_a, &vote_b)| a.weight * vote_a + b.weight * vote_b)
+        .collect();
+
+    let weight = votes.iter().fold(0.0, |acc: f64, &vote| acc.max(vote.abs()));
+    if weight == 0.0 {
+        return None;
+    }
+
+    let mut lbp = LbpClassifier::new(*a.feature(),
+                                     votes.iter().map(|&vote| vote / weight).collect());
+    lbp.weight = weight;
+
+    Some(WeakClassifier::Lbp(lbp))
+}
+
+// Some(merged) when `b` can be folded into `a`: stumps on the same boxes whose
+// thresholds split at most `max_disagreement` of the windows differently, or
+// lbp classifiers on the same blocks. the inner None is a merge that cancelled
+fn try_merge(a: &WeakClassifier,
+             b: &WeakClassifier,
+             image_collection: &Vec<DataPoint>,
+             max_disagreement: f64)
+             -> Option<Option<WeakClassifier>> {
+    match (a, b) {
+        (&WeakClassifier::Lbp(ref a), &WeakClassifier::Lbp(ref b)) => {
+            if a.feature() == b.feature() {
+                Some(merge_lbp(a, b))
+            } else {
+                None
+            }
+        }
+        _ => {
+            match (Stump::from_classifier(a), Stump::from_classifier(b)) {
+                (Some(a), Some(b)) => {
+                    if a.feature.same_boxes(&b.feature) &&
+                       a.disagreement(&b, image_collection) <= max_disagreement {
+                        Some(a.merge(b).into_classifier())
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            }
+        }
+    }
+}
+
+// folds each classifier into the first earlier one of the stage it duplicates,
+// then drops the classifiers weighing less than `min_alpha` of the stage. the
+// strongest one stays whatever its share, so that no stage is emptied by the
+// weights alone
+pub fn compress_stage(stage: Vec<WeakClassifier>,
+                      image_collection: &Vec<DataPoint>,
+                      max_disagreement: f64,
+                      min_alpha: f64,
+                      report: &mut CompressionReport)
+                      -> Vec<WeakClassifier> {
+    let mut kept: Vec<WeakClassifier> = Vec::new();
+
+    for classifier in stage.into_iter() {
+        let merge = kept.iter()
+            .enumerate()
+            .filter_map(|(i, earlier)| {
+                try_merge(earlier, &classifier, image_collection, max_disagreement)
+                    .map(|merged| (i, merged))
+            })
+            .next();
+
+        match merge {
+            Some((i, Some(merged))) => {
+                kept[i] = merged;
+                report.merged += 1;
+            }
+            Some((i, None)) => {
+                kept.remove(i);
+                report.merged += 1;
+                report.cancelled += 1;
+            }
+            None => kept.push(classifier),
+        }
+    }
+
+    let total = kept.iter().fold(0.0, |acc, h| acc + h.weight().abs());
+    let num_kept = kept.len();
+    let strongest = (0..num_kept).fold(0, |best, i| {
+        if kept[i].weight().abs() > kept[best].weight().abs() { i } else { best }
+    });
+
+    let kept: Vec<_> = kept.into_iter()
+        .enumerate()
+        .filter(|&(i, ref h)| i == strongest || h.weight().abs() >= min_alpha * total)
+        .map(|(_, h)| h)
+        .collect();
+    report.dropped += num_kept - kept.len();
+
+    kept
+}
+
+// compresses every stage of a cascade, or the sequence of a soft cascade,
+// whose rejection thresholds are calibrated again for `detection_rate`
+pub fn compress_model(model: Model,
+                      image_collection: &Vec<DataPoint>,
+                      max_disagreement: f64,
+                      min_alpha: f64,
+                      detection_rate: f64)
+                      -> (Model, CompressionReport) {
+    let mut report = CompressionReport::default();
+
+    let model = match model {
+        Model::Cascade(ensemble) => {
+            let mut compressed = PredictionEnsemble::new();
+
+            for (i, stage) in ensemble.into_stages().into_iter().enumerate() {
+                let stage = compress_stage(stage,
+                                           image_collection,
+                                           max_disagreement,
+                                           min_alpha,
+                                           &mut report);
+                assert!(!stage.is_empty(),
+                        "The weak classifiers of stage {} cancelled out; lower the disagreement \
+                         allowed",
+                        i);
+
+                compressed.push(stage);
+            }
+
+            Model::Cascade(compressed)
+        }
+        Model::SoftCascade(cascade) => {
+            let classifiers = compress_stage(cascade.into_classifiers(),
+                                             image_collection,
+                                             max_disagreement,
+                                             min_alpha,
+                                             &mut report);
+            assert!(!classifiers.is_empty(),
+                    "The weak classifiers of the soft cascade cancelled out; lower the \
+                     disagreement allowed");
+
+            let cascade = SoftCascade::calibrate(classifiers, image_collection, detection_rate);
+            Model::SoftCascade(cascade)
+        }
+        Model::Hog(_) => panic!("Only cascades of Haar-like or LBP features can be compressed"),
+    };
+
+    (model, report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lbp_feature::{LbpFeature, NUM_LBP_CODES};
+    use synthetic::{seeded_rng, synthetic_dataset};
+    use rand::Rng;
+
+    // a stump voting `positive` or `negative` times `weight`
+    fn stump(feature: &HaarLikeFeature,
+             threshold: f64,
+             positive: f64,
+             negative: f64,
+             weight: f64)
+             -> WeakClassifier {
+        let mut feature = feature.clone();
+        feature.threshold = threshold;
+
+        let mut tree = DecisionTree::new(DecisionNode::Split {
+            feature: feature,
+            positive: Box::new(DecisionNode::Leaf { label: positive }),
+            negative: Box::new(DecisionNode::Leaf { label: negative }),
+        });
+        tree.weight = weight;
+
+        WeakClassifier::Tree(tree)
+    }
+
+    fn sum(classifiers: &[&WeakClassifier], data_point: &DataPoint) -> f64 {
+        classifiers.iter().fold(0.0, |acc, h| acc + h.predict(&data_point.integral_image))
+    }
+
+    // the scores of the windows at a third and two thirds of their range
+    fn thresholds(feature: &HaarLikeFeature, image_collection: &Vec<DataPoint>) -> (f64, f64) {
+        let mut scores: Vec<_> = image_collection.iter()
+            .map(|data_point| feature.get_score(&data_point.integral_image))
+            .collect();
+        scores.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        (scores[scores.len() / 3], scores[2 * scores.len() / 3])
+    }
+
+    // the votes of `merged` and of the pair it replaces agree on every window
+    // outside the band between the two thresholds
+    fn assert_same_votes_outside(merged: &[&WeakClassifier],
+                                 pair: &[&WeakClassifier],
+                                 feature: &HaarLikeFeature,
+                                 band: (f64, f64),
+                                 image_collection: &Vec<DataPoint>) {
+        let mut outside = 0;
+
+        for data_point in image_collection.iter() {
+            let score = feature.get_score(&data_point.integral_image);
+            if score >= band.0 && score < band.1 {
+                continue;
+            }
+
+            outside += 1;
+            assert!((sum(merged, data_point) - sum(pair, data_point)).abs() < 1e-9,
+                    "score {}",
+                    score);
+        }
+
+        assert!(outside > 0);
+    }
+
+    #[test]
+    fn merged_stumps_vote_alike_outside_their_thresholds() {
+        let (image_collection, _, _) = synthetic_dataset(0, 30, 30);
+        let features = HaarLikeFeature::generate_all_features();
+        // the same boxes with both polarities
+        let (positive, negative) = (&features[0], &features[1]);
+        let (low, high) = thresholds(positive, &image_collection);
+
+        for &(a, b) in &[(positive, positive), (positive, negative), (negative, positive)] {
+            let first = stump(a, low, 1.0, -0.5, 0.8);
+            let second = stump(b, high, 0.25, -1.0, 1.5);
+
+            let merged = Stump::from_classifier(&first)
+                .unwrap()
+                .merge(Stump::from_classifier(&second).unwrap())
+                .into_classifier()
+                .unwrap();
+
+            assert_same_votes_outside(&[&merged],
+                                      &[&first, &second],
+                                      a,
+                                      (low, high),
+                                      &image_collection);
+        }
+    }
+
+    #[test]
+    fn merged_lbp_tables_vote_alike_everywhere() {
+        let (image_collection, _, _) = synthetic_dataset(0, 20, 20);
+        let feature = LbpFeature::generate_all_features()[40];
+        let mut rng = seeded_rng(0);
+
+        let mut table = || (0..NUM_LBP_CODES).map(|_| rng.gen_range(-1.0, 1.0)).collect();
+
+        let mut a = LbpClassifier::new(feature, table());
+        let mut b = LbpClassifier::new(feature, table());
+        a.weight = 0.7;
+        b.weight = 1.3;
+
+        let merged = merge_lbp(&a, &b).unwrap();
+        for data_point in image_collection.iter() {
+            let integral_image = &data_point.integral_image;
+            assert!((merged.predict(integral_image) - a.predict(integral_image) -
+                     b.predict(integral_image))
+                .abs() < 1e-9);
+        }
+
+        // the same votes with the opposite sign leave nothing
+        let mut opposite = LbpClassifier::new(feature, a.table().clone());
+        opposite.weight = -a.weight;
+        assert!(merge_lbp(&a, &opposite).is_none());
+    }
+
+    #[test]
+    fn compressed_stages_vote_alike_outside_the_merged_thresholds() {
+        let (image_collection, _, _) = synthetic_dataset(1, 30, 30);
+        let features = HaarLikeFeature::generate_all_features();
+        let (feature, other) = (&features[0], &features[2]);
+        let (low, high) = thresholds(feature, &image_collection);
+
+        let stage = || {
+            vec![stump(feature, low, 1.0, -1.0, 0.6),
+                 stump(other, 0.0, 1.0, -1.0, 0.4),
+                 stump(feature, high, 1.0, -1.0, 0.9)]
+        };
+
+        let mut report = CompressionReport::default();
+        let compressed = compress_stage(stage(), &image_collection, 1.0, 0.0, &mut report);
+
+        assert_eq!((compressed.len(), report.merged, report.cancelled), (2, 1, 0));
+        assert_same_votes_outside(&compressed.iter().collect::<Vec<_>>(),
+                                  &stage().iter().collect::<Vec<_>>(),
+                                  feature,
+                                  (low, high),
+                                  &image_collection);
+
+        // thresholds splitting more windows than allowed stay apart
+        let compressed = compress_stage(stage(), &image_collection, 0.0, 0.0, &mut report);
+        assert_eq!(compressed.len(), 3);
+    }
+
+    #[test]
+    fn the_strongest_classifier_is_always_kept() {
+        let (image_collection, _, _) = synthetic_dataset(0, 10, 10);
+        let features = HaarLikeFeature::generate_all_features();
+
+        // every classifier is below half of the weight of the stage
+        let stage: Vec<_> = (0..3)
+            .map(|i| stump(&features[2 * i], 0.0, 1.0, -1.0, 1.0 + i as f64 / 10.0))
+            .collect();
+
+        let mut report = CompressionReport::default();
+        let compressed = compress_stage(stage, &image_collection, 0.0, 0.5, &mut report);
+
+        assert_eq!((compressed.len(), report.dropped), (1, 2));
+        assert_eq!(compressed[0].weight(), 1.2);
+    }
+
+    #[test]
+    #[should_panic(expected = "cancelled out")]
+    fn a_soft_cascade_that_cancels_out_is_reported() {
+        let (image_collection, _, _) = synthetic_dataset(0, 10, 10);
+        let feature = &HaarLikeFeature::generate_all_features()[0];
+
+        let classifiers = vec![stump(feature, 0.0, 1.0, -1.0, 1.0),
+                               stump(feature, 0.0, -1.0, 1.0, 1.0)];
+        let cascade = SoftCascade::calibrate(classifiers, &image_collection, 1.0);
+
+        compress_model(Model::SoftCascade(cascade), &image_collection, 1.0, 0.0, 1.0);
+    }
+}
@@ -0,0 +1,67 @@
+use integral_image::IntegralImage;
+use haar_like_feature::HaarLikeFeature;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum DecisionNode {
+    // the feature's own prediction decides which branch a window follows
+    Split {
+        feature: HaarLikeFeature,
+        positive: Box<DecisionNode>,
+        negative: Box<DecisionNode>,
+    },
+    Leaf { label: f64 },
+}
+
+impl DecisionNode {
+    pub fn classify(&self, integral_image: &IntegralImage) -> f64 {
+        match *self {
+            DecisionNode::Split { ref feature, ref positive, ref negative } => {
+                if feature.predict(integral_image) > 0.0 {
+                    positive.classify(integral_image)
+                } else {
+                    negative.classify(integral_image)
+                }
+            }
+            DecisionNode::Leaf { label } => label,
+        }
+    }
+
+    fn collect_features<'a>(&'a self, features: &mut Vec<&'a HaarLikeFeature>) {
+        if let DecisionNode::Split { ref feature, ref positive, ref negative } = *self {
+            features.push(feature);
+            positive.collect_features(features);
+            negative.collect_features(features);
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DecisionTree {
+    pub weight: f64,
+    root: DecisionNode,
+}
+
+impl DecisionTree {
+    pub fn new(root: DecisionNode) -> DecisionTree {
+        DecisionTree {
+            weight: 1.0,
+            root: root,
+        }
+    }
+
+    pub fn root(&self) -> &DecisionNode {
+        &self.root
+    }
+
+    // split features in depth-first order
+    pub fn features(&self) -> Vec<&HaarLikeFeature> {
+        let mut features = Vec::new();
+        self.root.collect_features(&mut features);
+
+        features
+    }
+
+    pub fn predict(&self, integral_image: &IntegralImage) -> f64 {
+        self.weight * self.root.classify(integral_image)
+    }
+}
@@ -0,0 +1,136 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_json;
+extern crate image;
+extern crate rulinalg;
+extern crate rand;
+
+mod load;
+mod pgm;
+mod manifest;
+mod synthetic;
+mod frame_source;
+mod shared;
+mod geometry;
+mod integral_image;
+mod haar_like_feature;
+mod decision_tree;
+mod lbp_feature;
+mod weak_classifier;
+mod prediction_ensemble;
+mod soft_cascade;
+mod detector;
+mod fast_integral_image;
+mod compiled_cascade;
+mod linear_svm;
+mod hog;
+mod model;
+mod detection_params;
+mod prefilter;
+mod scanner;
+mod annotations;
+mod warp;
+mod landmarks;
+mod bundle;
+mod args;
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use frame_source::{Frame, FrameSource};
+use landmarks::LandmarkRegressor;
+use model::Model;
+use bundle::{BundleEntry, DetectorBundle};
+use detection_params::DetectionParams;
+use prefilter::{Prefilters, coverage};
+use scanner::Detection;
+
+#[derive(Serialize, Debug)]
+struct FrameDetections {
+    frame: usize,
+    name: String,
+    detections: Vec<Detection>,
+}
+
+fn main() {
+    let input = args::get_option::<String>("input").expect("Missing --input INPUT");
+    let model_path = args::get_option("model").unwrap_or("./foo.json".to_string());
+    let output = args::get_option("output").unwrap_or("detections.jsonl".to_string());
+    let min_neighbours = args::get_option("min-neighbours").unwrap_or(3);
+
+    let raw_size = args::get_option("width").and_then(|width| {
+        args::get_option("height").map(|height| (width, height))
+    });
+
+    // a single model is a bundle of one frontal cascade
+    let bundle = match args::get_option::<String>("bundle") {
+        Some(bundle_path) => DetectorBundle::load(&bundle_path),
+        None => {
+            DetectorBundle::new(vec![BundleEntry {
+                                         label: "face".to_string(),
+                                         model: Model::load(&model_path),
+                                         mirror: false,
+                                     }])
+        }
+    };
+
+    let params = DetectionParams::from_args();
+    let mut prefilters = Prefilters::from_args();
+
+    let labels: Vec<_> = bundle.entries().iter().map(|entry| entry.label.as_str()).collect();
+    println!("Cascades: {}", labels.join(", "));
+
+    let regressor = args::get_option::<String>("landmarks")
+        .map(|landmarks_path| LandmarkRegressor::load(&landmarks_path));
+
+    // one json object per line, written as soon as the frame is done
+    let f = File::create(&output).expect("Unable to create file");
+    let mut writer = BufWriter::new(f);
+
+    for (frame, Frame { name, gray: data, colour, error }) in FrameSource::open(&input, raw_size)
+        .enumerate() {
+        // an undecodable frame still gets its record, so that the numbers of
+        // the records keep matching the frames of the video
+        if let Some(error) = error {
+            println!("Frame {}: unable to decode, {}", frame, error);
+
+            let record = FrameDetections {
+                frame: frame,
+                name: name,
+                detections: Vec::new(),
+            };
+            writeln!(writer, "{}", serde_json::to_string(&record).unwrap())
+                .expect("Unable to write data");
+            continue;
+        }
+
+        let candidates = prefilters.mask(&data, colour.as_ref());
+        let mut detections =
+            bundle.detect(&data, candidates.as_ref(), &params, 0.3, min_neighbours);
+
+        if let Some(ref regressor) = regressor {
+            for detection in detections.iter_mut() {
+                detection.landmarks = Some(regressor.predict(&data, &detection.rect));
+            }
+        }
+
+        match candidates {
+            Some(ref candidates) => {
+                println!("Frame {}: {} detections, {:.0}% of the picture left by the prefilters",
+                         frame,
+                         detections.len(),
+                         coverage(candidates) * 100.0)
+            }
+            None => println!("Frame {}: {} detections", frame, detections.len()),
+        }
+
+        let record = FrameDetections {
+            frame: frame,
+            name: name,
+            detections: detections,
+        };
+
+        writeln!(writer, "{}", serde_json::to_string(&record).unwrap())
+            .expect("Unable to write data");
+    }
+}
@@ -0,0 +1,253 @@
+use geometry::{Rect, Size};
+use scanner::Detection;
+use shared::{WINDOW_HEIGHT, WINDOW_WIDTH};
+use args;
+
+// where and at which sizes to look for faces. the defaults scan every
+// position of the whole picture with the 19x19 window only
+#[derive(Debug, Clone)]
+pub struct DetectionParams {
+    // only windows inside it are scanned
+    pub roi: Option<Rect>,
+    // width of the smallest window
+    pub min_size: usize,
+    // width of the largest window; without one only `min_size` is scanned
+    pub max_size: Option<usize>,
+    // ratio between the sizes of two consecutive windows
+    pub scale_factor: f64,
+    // distance between two windows in pixels of a 19x19 window, growing with
+    // the window
+    pub step_x: f64,
+    pub step_y: f64,
+    // highest scoring detections kept
+    pub max_detections: Option<usize>,
+    // clockwise turns in degrees of the picture scanned besides the upright
+    // one, for tilted heads
+    pub angles: Vec<f64>,
+    // share of a window the prefilters have to keep for it to be evaluated
+    pub min_coverage: f64,
+}
+
+impl Default for DetectionParams {
+    fn default() -> DetectionParams {
+        DetectionParams {
+            roi: None,
+            min_size: WINDOW_WIDTH,
+            max_size: None,
+            scale_factor: 1.25,
+            step_x: 1.0,
+            step_y: 1.0,
+            max_detections: None,
+            angles: Vec::new(),
+            min_coverage: 0.4,
+        }
+    }
+}
+
+// one window size of the scan
+#[derive(Debug, Copy, Clone)]
+pub struct ScanLevel {
+    // window size over 19
+    pub scale: f64,
+    pub window: Size,
+    pub step_x: usize,
+    pub step_y: usize,
+}
+
+impl ScanLevel {
+    pub fn new(scale: f64, step_x: f64, step_y: f64) -> ScanLevel {
+        ScanLevel {
+            scale: scale,
+            window: Size::new((WINDOW_WIDTH as f64 * scale).round() as usize,
+                              (WINDOW_HEIGHT as f64 * scale).round() as usize),
+            step_x: ((step_x * scale).round() as usize).max(1),
+            step_y: ((step_y * scale).round() as usize).max(1),
+        }
+    }
+}
+
+impl DetectionParams {
+    // --roi x,y,width,height --min-size --max-size --scale-factor --step-x
+    // --step-y --max-detections --angles a,b,... --min-coverage
+    pub fn from_args() -> DetectionParams {
+        let defaults = DetectionParams::default();
+
+        let roi = args::get_option::<String>("roi").map(|roi| {
+            let parts: Vec<usize> = roi.split(',')
+                .map(|part| part.trim().parse().expect("Invalid value for --roi"))
+                .collect();
+            assert!(parts.len() == 4, "--roi takes x,y,width,height");
+
+            Rect::new(parts[0], parts[1], parts[2], parts[3])
+        });
+
+        let angles = args::get_option::<String>("angles").map_or(Vec::new(), |angles| {
+            angles.split(',')
+                .map(|angle| angle.trim().parse().expect("Invalid value for --angles"))
+                .filter(|&angle: &f64| angle != 0.0)
+                .collect()
+        });
+
+        let params = DetectionParams {
+            roi: roi,
+            min_size: args::get_option("min-size").unwrap_or(defaults.min_size),
+            max_size: args::get_option("max-size"),
+            scale_factor: args::get_option("scale-factor").unwrap_or(defaults.scale_factor),
+            step_x: args::get_option("step-x").unwrap_or(defaults.step_x),
+            step_y: args::get_option("step-y").unwrap_or(defaults.step_y),
+            max_detections: args::get_option("max-detections"),
+            angles: angles,
+            min_coverage: args::get_option("min-coverage").unwrap_or(defaults.min_coverage),
+        };
+
+        if let Err(message) = params.check_sizes() {
+            panic!("{}", message);
+        }
+
+        params
+    }
+
+    // windows below the size of the model cannot be scanned, a largest window
+    // below the smallest one would scan nothing, and sizes that do not grow or
+    // steps that do not move would never end
+    pub fn check_sizes(&self) -> Result<(), String> {
+        if self.scale_factor.is_nan() || self.scale_factor <= 1.0 {
+            return Err(format!("The scale factor has to be above 1, got {}", self.scale_factor));
+        }
+
+        if [self.step_x, self.step_y].iter().any(|step| step.is_nan() || *step <= 0.0) {
+            return Err(format!("The steps have to be positive, got {} and {}",
+                               self.step_x,
+                               self.step_y));
+        }
+
+        if self.min_size < WINDOW_WIDTH {
+            return Err(format!("The minimum size has to be at least {}, got {}",
+                               WINDOW_WIDTH,
+                               self.min_size));
+        }
+
+        match self.max_size {
+            Some(max_size) if max_size < self.min_size => {
+                Err(format!("The maximum size {} is below the minimum size {}",
+                            max_size,
+                            self.min_size))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // the region of interest clipped to the picture, None if nothing is left
+    pub fn region(&self, width: usize, height: usize) -> Option<Rect> {
+        let bounds = Rect::new(0, 0, width, height);
+        let roi = self.roi.unwrap_or(bounds);
+
+        if !roi.intersects(&bounds) {
+            return None;
+        }
+
+        let (x, y) = (roi.x.min(width), roi.y.min(height));
+        Some(Rect::new(x, y, roi.right().min(width) - x, roi.bottom().min(height) - y))
+    }
+
+    // window sizes from the smallest up that fit in the region
+    pub fn levels(&self, region: Size) -> Vec<ScanLevel> {
+        assert!(self.scale_factor > 1.0,
+                "The scale factor has to be above 1, got {}",
+                self.scale_factor);
+
+        let max_size = self.max_size.unwrap_or(self.min_size);
+        let mut scale = self.min_size as f64 / WINDOW_WIDTH as f64;
+        let mut levels = Vec::new();
+
+        loop {
+            let level = ScanLevel::new(scale, self.step_x, self.step_y);
+            let window = level.window;
+
+            if window.width > max_size || window.width > region.width ||
+               window.height > region.height {
+                break;
+            }
+
+            // rounding can give the same window twice at small scales
+            if levels.last().map_or(true, |last: &ScanLevel| last.window != window) {
+                levels.push(level);
+            }
+
+            scale *= self.scale_factor;
+        }
+
+        levels
+    }
+
+    // the `max_detections` highest scoring detections, best first
+    pub fn keep_best(&self, mut detections: Vec<Detection>) -> Vec<Detection> {
+        if let Some(max_detections) = self.max_detections {
+            detections.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+            detections.truncate(max_detections);
+        }
+
+        detections
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sizes(min_size: usize, max_size: Option<usize>) -> DetectionParams {
+        DetectionParams {
+            min_size: min_size,
+            max_size: max_size,
+            ..DetectionParams::default()
+        }
+    }
+
+    #[test]
+    fn windows_smaller_than_the_model_are_refused() {
+        assert!(sizes(WINDOW_WIDTH, None).check_sizes().is_ok());
+        assert!(sizes(WINDOW_WIDTH, Some(WINDOW_WIDTH)).check_sizes().is_ok());
+        assert!(sizes(1, None).check_sizes().is_err());
+        assert!(sizes(WINDOW_WIDTH - 1, Some(40)).check_sizes().is_err());
+        assert!(sizes(40, Some(39)).check_sizes().is_err());
+    }
+
+    #[test]
+    fn scale_factors_and_steps_are_checked() {
+        let params = |scale_factor, step_x, step_y| {
+            DetectionParams {
+                scale_factor: scale_factor,
+                step_x: step_x,
+                step_y: step_y,
+                ..DetectionParams::default()
+            }
+        };
+
+        assert!(params(1.1, 0.5, 2.0).check_sizes().is_ok());
+        assert_eq!(params(1.0, 1.0, 1.0).check_sizes(),
+                   Err("The scale factor has to be above 1, got 1".to_string()));
+        assert!(params(0.8, 1.0, 1.0).check_sizes().is_err());
+        assert!(params(::std::f64::NAN, 1.0, 1.0).check_sizes().is_err());
+        assert_eq!(params(1.25, 0.0, 1.0).check_sizes(),
+                   Err("The steps have to be positive, got 0 and 1".to_string()));
+        assert!(params(1.25, 1.0, -1.0).check_sizes().is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "The scale factor has to be above 1, got 1")]
+    fn levels_refuse_a_scale_factor_that_does_not_grow() {
+        let params = DetectionParams { scale_factor: 1.0, ..sizes(24, Some(60)) };
+        params.levels(Size::new(100, 100));
+    }
+
+    #[test]
+    fn levels_grow_from_the_minimum_to_the_maximum_size() {
+        let widths: Vec<_> = sizes(24, Some(60))
+            .levels(Size::new(100, 100))
+            .iter()
+            .map(|level| level.window.width)
+            .collect();
+
+        assert_eq!(widths, vec![24, 30, 38, 47, 59]);
+    }
+}
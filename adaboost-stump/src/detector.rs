@@ -0,0 +1,23 @@
+use integral_image::IntegralImage;
+use compiled_cascade::{CompiledCascade, WindowLayout};
+use shared::ImageData;
+
+// anything that can tell whether a WINDOW_WIDTH x WINDOW_HEIGHT window is a face
+pub trait Detector {
+    fn predict_window(&self, image_data: &ImageData, integral_image: &IntegralImage) -> bool;
+
+    // how far above its last threshold an accepted window scored, none if rejected
+    fn score_window(&self, image_data: &ImageData, integral_image: &IntegralImage) -> Option<f64> {
+        if self.predict_window(image_data, integral_image) {
+            Some(0.0)
+        } else {
+            None
+        }
+    }
+
+    // the same detector evaluated on the integer integral image of the whole
+    // picture, for those that can be
+    fn compile(&self, _layout: &WindowLayout) -> Option<CompiledCascade> {
+        None
+    }
+}
@@ -0,0 +1,85 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_json;
+extern crate image;
+extern crate rulinalg;
+extern crate rand;
+
+mod shared;
+mod geometry;
+mod integral_image;
+mod prediction_ensemble;
+mod soft_cascade;
+mod haar_like_feature;
+mod decision_tree;
+mod lbp_feature;
+mod weak_classifier;
+mod detector;
+mod fast_integral_image;
+mod compiled_cascade;
+mod linear_svm;
+mod hog;
+mod model;
+mod detection_params;
+mod prefilter;
+mod scanner;
+mod annotations;
+mod warp;
+mod diagnostics;
+mod args;
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use model::Model;
+use detection_params::DetectionParams;
+use diagnostics::{Summary, depth_overlay, diagnose, heatmap_overlay};
+
+#[derive(Serialize, Debug)]
+struct Report {
+    image: String,
+    model: String,
+    summary: Summary,
+}
+
+fn main() {
+    let image_path = args::get_option("image").unwrap_or("./data/got.jpeg".to_string());
+    let model_path = args::get_option("model").unwrap_or("./foo.json".to_string());
+    let output = args::get_option("output").unwrap_or("./diagnostics".to_string());
+
+    let data = image::open(&image_path).unwrap().to_luma();
+    let model = Model::load(&model_path);
+    let params = DetectionParams::from_args();
+
+    let (summary, maps) = diagnose(&model, &data, &params);
+
+    println!("{} windows, {} accepted", summary.windows, summary.accepted);
+    for stats in summary.stages.iter() {
+        println!("Stage {}: {} reached, {} passed ({:.1}%)",
+                 stats.stage,
+                 stats.reached,
+                 stats.passed,
+                 stats.pass_rate * 100.0);
+    }
+
+    let output = Path::new(&output);
+    fs::create_dir_all(output).expect("Unable to create the output folder");
+
+    depth_overlay(&data, &maps, summary.stages.len())
+        .save(output.join("depth.png"))
+        .expect("Unable to write depth.png");
+    heatmap_overlay(&data, &maps)
+        .save(output.join("heatmap.png"))
+        .expect("Unable to write heatmap.png");
+
+    let report = Report {
+        image: image_path,
+        model: model_path,
+        summary: summary,
+    };
+    let serialized = serde_json::to_string_pretty(&report).unwrap();
+
+    let mut f = File::create(output.join("diagnostics.json")).expect("Unable to create file");
+    f.write_all(serialized.as_bytes()).expect("Unable to write data");
+}
@@ -0,0 +1,303 @@
+use image::{GrayImage, Pixel, Rgb, RgbImage};
+use fast_integral_image::FastIntegralImage;
+use compiled_cascade::WindowLayout;
+use detection_params::DetectionParams;
+use detector::Detector;
+use scanner::{crop, positions, window_maxima};
+
+// how strongly the maps cover the picture
+const OVERLAY_OPACITY: f64 = 0.6;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct StageStats {
+    pub stage: usize,
+    // windows evaluated by the stage
+    pub reached: usize,
+    pub passed: usize,
+    pub rejected: usize,
+    pub pass_rate: f64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Summary {
+    pub width: u32,
+    pub height: u32,
+    pub windows: usize,
+    pub accepted: usize,
+    pub stages: Vec<StageStats>,
+}
+
+// one value per pixel of the picture, row after row
+#[derive(Debug)]
+pub struct DiagnosticMaps {
+    pub width: usize,
+    // stages passed by the window centred on the pixel, None where no window is
+    pub depth: Vec<Option<usize>>,
+    // margins of the accepted windows covering the pixel, added up
+    pub confidence: Vec<f64>,
+}
+
+// runs the compiled cascade over every window `params` describes (without the
+// turned copies) and keeps how deep each window got
+pub fn diagnose<D: Detector>(detector: &D,
+                             data: &GrayImage,
+                             params: &DetectionParams)
+                             -> (Summary, DiagnosticMaps) {
+    let (width, height) = (data.width() as usize, data.height() as usize);
+
+    let mut maps = DiagnosticMaps {
+        width: width,
+        depth: vec![None; width * height],
+        confidence: vec![0.0; width * height],
+    };
+
+    // added at the corners of the accepted windows, summed up at the end
+    let mut confidence_corners = vec![0.0; (width + 1) * (height + 1)];
+
+    let mut passed_counts = Vec::new();
+    let (mut windows, mut accepted) = (0, 0);
+
+    if let Some(region) = params.region(width, height) {
+        let cropped = crop(data, &region);
+        let integral_image = FastIntegralImage::build(&cropped);
+
+        for level in params.levels(region.size()).iter() {
+            let layout = WindowLayout::new(integral_image.stride(), level.scale);
+            let cascade = detector.compile(&layout)
+                .expect("Only cascades of Haar-like or LBP features can be diagnosed");
+
+            if passed_counts.is_empty() {
+                passed_counts = vec![0; cascade.num_stages() + 1];
+            }
+
+            let window = level.window;
+            if region.width < window.width || region.height < window.height {
+                continue;
+            }
+
+            let maxima = window_maxima(&cropped, window);
+            let positions_x = region.width - window.width + 1;
+
+            for y in positions(region.height, window.height, level.step_y) {
+                for x in positions(region.width, window.width, level.step_x) {
+                    let max = (maxima[y * positions_x + x] as f64).max(1.0);
+                    let (passed, margin) = cascade.evaluate(&integral_image,
+                                                            integral_image.origin(x, y),
+                                                            max);
+
+                    windows += 1;
+                    passed_counts[passed] += 1;
+
+                    let (left, top) = (region.x + x, region.y + y);
+                    let center = (top + window.height / 2) * width + left + window.width / 2;
+                    maps.depth[center] = Some(maps.depth[center].map_or(passed, |depth| {
+                        depth.max(passed)
+                    }));
+
+                    if passed == cascade.num_stages() {
+                        accepted += 1;
+
+                        let (right, bottom) = (left + window.width, top + window.height);
+                        confidence_corners[top * (width + 1) + left] += margin;
+                        confidence_corners[top * (width + 1) + right] -= margin;
+                        confidence_corners[bottom * (width + 1) + left] -= margin;
+                        confidence_corners[bottom * (width + 1) + right] += margin;
+                    }
+                }
+            }
+        }
+    }
+
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = confidence_corners[y * (width + 1) + x];
+            if x > 0 {
+                sum += maps.confidence[y * width + x - 1];
+            }
+            if y > 0 {
+                sum += maps.confidence[(y - 1) * width + x];
+            }
+            if x > 0 && y > 0 {
+                sum -= maps.confidence[(y - 1) * width + x - 1];
+            }
+
+            maps.confidence[y * width + x] = sum;
+        }
+    }
+
+    // a window that passed n stages reached the first n + 1
+    let num_stages = passed_counts.len().saturating_sub(1);
+    let stages = (0..num_stages)
+        .map(|stage| {
+            let reached: usize = passed_counts[stage..].iter().sum();
+            let passed = reached - passed_counts[stage];
+
+            StageStats {
+                stage: stage + 1,
+                reached: reached,
+                passed: passed,
+                rejected: reached - passed,
+                pass_rate: if reached > 0 { passed as f64 / reached as f64 } else { 0.0 },
+            }
+        })
+        .collect();
+
+    let summary = Summary {
+        width: data.width(),
+        height: data.height(),
+        windows: windows,
+        accepted: accepted,
+        stages: stages,
+    };
+
+    (summary, maps)
+}
+
+// blue for 0 through green to red for 1
+fn ramp(t: f64) -> [f64; 3] {
+    [(2.0 * t - 1.0).max(0.0) * 255.0,
+     (1.0 - (2.0 * t - 1.0).abs()) * 255.0,
+     (1.0 - 2.0 * t).max(0.0) * 255.0]
+}
+
+fn blend(gray: u8, colour: [f64; 3], opacity: f64) -> Rgb<u8> {
+    let mix = |channel: f64| (gray as f64 * (1.0 - opacity) + channel * opacity).round() as u8;
+    Rgb([mix(colour[0]), mix(colour[1]), mix(colour[2])])
+}
+
+// the picture with the depth of the window centred on each pixel, blue for
+// rejected by the first stage up to red for accepted
+pub fn depth_overlay(data: &GrayImage, maps: &DiagnosticMaps, num_stages: usize) -> RgbImage {
+    RgbImage::from_fn(data.width(), data.height(), |x, y| {
+        let gray = data.get_pixel(x, y).channels()[0];
+
+        match maps.depth[y as usize * maps.width + x as usize] {
+            Some(depth) => {
+                let t = depth as f64 / num_stages.max(1) as f64;
+                blend(gray, ramp(t), OVERLAY_OPACITY)
+            }
+            None => Rgb([gray, gray, gray]),
+        }
+    })
+}
+
+// the picture turning red where accepted windows pile up with high margins
+pub fn heatmap_overlay(data: &GrayImage, maps: &DiagnosticMaps) -> RgbImage {
+    let hottest = maps.confidence.iter().fold(0.0, |acc: f64, &value| acc.max(value));
+
+    RgbImage::from_fn(data.width(), data.height(), |x, y| {
+        let gray = data.get_pixel(x, y).channels()[0];
+        let heat = if hottest > 0.0 {
+            maps.confidence[y as usize * maps.width + x as usize] / hottest
+        } else {
+            0.0
+        };
+
+        blend(gray, [255.0, 0.0, 0.0], heat * OVERLAY_OPACITY)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integral_image::IntegralImage;
+    use compiled_cascade::CompiledCascade;
+    use decision_tree::{DecisionNode, DecisionTree};
+    use weak_classifier::WeakClassifier;
+    use shared::ImageData;
+
+    // a cascade whose stages vote the same for every window: +1 passes, -1
+    // rejects, so every window gets exactly as deep as the first -1
+    struct Stub {
+        votes: Vec<f64>,
+    }
+
+    impl Stub {
+        fn stages(&self) -> Vec<Vec<WeakClassifier>> {
+            self.votes
+                .iter()
+                .map(|&vote| {
+                    vec![WeakClassifier::Tree(DecisionTree::new(DecisionNode::Leaf {
+                             label: vote,
+                         }))]
+                })
+                .collect()
+        }
+    }
+
+    impl Detector for Stub {
+        fn predict_window(&self, _: &ImageData, _: &IntegralImage) -> bool {
+            self.votes.iter().all(|&vote| vote > 0.0)
+        }
+
+        fn compile(&self, layout: &WindowLayout) -> Option<CompiledCascade> {
+            Some(CompiledCascade::from_stages(&self.stages(), layout))
+        }
+    }
+
+    fn gray(width: u32, height: u32) -> GrayImage {
+        GrayImage::from_pixel(width, height, image::Luma([100]))
+    }
+
+    #[test]
+    fn windows_are_as_deep_as_the_stages_they_pass() {
+        let stub = Stub { votes: vec![1.0, 1.0, -1.0] };
+        let data = gray(25, 21);
+
+        let (summary, maps) = diagnose(&stub, &data, &DetectionParams::default());
+
+        // 7 x 3 positions of the 19x19 window
+        assert_eq!((summary.windows, summary.accepted), (21, 0));
+        let counts: Vec<_> = summary.stages
+            .iter()
+            .map(|stats| (stats.stage, stats.reached, stats.passed, stats.rejected))
+            .collect();
+        assert_eq!(counts, vec![(1, 21, 21, 0), (2, 21, 21, 0), (3, 21, 0, 21)]);
+        assert_eq!(summary.stages[2].pass_rate, 0.0);
+
+        // the centres of the windows, 9 pixels in from their corner
+        for y in 0..21 {
+            for x in 0..25 {
+                let centre = x >= 9 && x < 16 && y >= 9 && y < 12;
+                assert_eq!(maps.depth[y * 25 + x],
+                           if centre { Some(2) } else { None },
+                           "at {}, {}",
+                           x,
+                           y);
+            }
+        }
+        assert!(maps.confidence.iter().all(|&confidence| confidence == 0.0));
+
+        // two stages out of three is a third of the way from green to red
+        let overlay = depth_overlay(&data, &maps, 3);
+        assert_eq!(*overlay.get_pixel(0, 0), Rgb([100, 100, 100]));
+        assert_eq!(*overlay.get_pixel(12, 10), Rgb([91, 142, 40]));
+    }
+
+    #[test]
+    fn the_heatmap_adds_up_the_margins_of_the_accepted_windows() {
+        let stub = Stub { votes: vec![1.0, 1.0] };
+        let data = gray(21, 21);
+
+        let (summary, maps) = diagnose(&stub, &data, &DetectionParams::default());
+
+        assert_eq!((summary.windows, summary.accepted), (9, 9));
+        assert!(summary.stages.iter().all(|stats| stats.pass_rate == 1.0));
+
+        // each of the 3 x 3 windows has a margin of 1
+        assert_eq!(maps.confidence[0], 1.0);
+        assert_eq!(maps.confidence[1], 2.0);
+        assert_eq!(maps.confidence[2 * 21 + 2], 9.0);
+        assert_eq!(maps.confidence[10 * 21 + 10], 9.0);
+        assert_eq!(maps.confidence[20 * 21 + 19], 2.0);
+        assert_eq!(maps.depth[9 * 21 + 9], Some(2));
+
+        // the hottest pixels get the full opacity, the others their share of it
+        let heatmap = heatmap_overlay(&data, &maps);
+        assert_eq!(*heatmap.get_pixel(10, 10), Rgb([193, 40, 40]));
+        assert_eq!(*heatmap.get_pixel(0, 0), Rgb([110, 93, 93]));
+
+        // accepted windows are red on the depth map too
+        assert_eq!(*depth_overlay(&data, &maps, 2).get_pixel(10, 10), Rgb([193, 40, 40]));
+    }
+}
@@ -0,0 +1,49 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_json;
+extern crate image;
+extern crate rulinalg;
+extern crate rand;
+
+mod load;
+mod pgm;
+mod manifest;
+mod synthetic;
+mod shared;
+mod geometry;
+mod integral_image;
+mod haar_like_feature;
+mod decision_tree;
+mod lbp_feature;
+mod weak_classifier;
+mod prediction_ensemble;
+mod soft_cascade;
+mod detector;
+mod fast_integral_image;
+mod compiled_cascade;
+mod linear_svm;
+mod hog;
+mod model;
+mod evaluation;
+mod args;
+
+use load::get_data_from_args;
+use model::Model;
+use evaluation::evaluate;
+
+fn main() {
+    let model_path = args::get_option("model").unwrap_or("./foo.json".to_string());
+    let model = Model::load(&model_path);
+
+    let (image_collection, num_faces, num_non_faces) =
+        get_data_from_args("./data/testset", "test");
+
+    let evaluation = evaluate(&model, &image_collection);
+
+    println!("Model: {}", model_path);
+    println!("Faces: {}, non-faces: {}", num_faces, num_non_faces);
+    println!("{:?}", evaluation);
+    println!("Detection rate: {:.4}", evaluation.detection_rate());
+    println!("False positive rate: {:.4}", evaluation.false_positive_rate());
+}
@@ -0,0 +1,39 @@
+use detector::Detector;
+use shared::DataPoint;
+
+#[derive(Debug, Default)]
+pub struct Evaluation {
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub true_negatives: usize,
+    pub false_negatives: usize,
+}
+
+impl Evaluation {
+    pub fn detection_rate(&self) -> f64 {
+        self.true_positives as f64 / ((self.true_positives + self.false_negatives) as f64)
+    }
+
+    pub fn false_positive_rate(&self) -> f64 {
+        self.false_positives as f64 / ((self.false_positives + self.true_negatives) as f64)
+    }
+}
+
+// classifies every labelled window and counts the outcomes
+pub fn evaluate<D: Detector>(detector: &D, image_collection: &Vec<DataPoint>) -> Evaluation {
+    let mut evaluation = Evaluation::default();
+
+    for data_point in image_collection.iter() {
+        let prediction = detector.predict_window(&data_point.image_data,
+                                                 &data_point.integral_image);
+
+        match (data_point.label > 0.0, prediction) {
+            (true, true) => evaluation.true_positives += 1,
+            (true, false) => evaluation.false_negatives += 1,
+            (false, true) => evaluation.false_positives += 1,
+            (false, false) => evaluation.true_negatives += 1,
+        }
+    }
+
+    evaluation
+}
@@ -0,0 +1,115 @@
+use image::GrayImage;
+use geometry::Rect;
+
+// corners of a rectangle as offsets into the flat buffer, relative to the
+// upper left corner of the window it belongs to
+#[derive(Debug, Copy, Clone)]
+pub struct RectOffsets {
+    upper_left: usize,
+    upper_right: usize,
+    bottom_left: usize,
+    bottom_right: usize,
+}
+
+impl RectOffsets {
+    pub fn new(rect: &Rect, stride: usize) -> RectOffsets {
+        RectOffsets {
+            upper_left: rect.y * stride + rect.x,
+            upper_right: rect.y * stride + rect.right(),
+            bottom_left: rect.bottom() * stride + rect.x,
+            bottom_right: rect.bottom() * stride + rect.right(),
+        }
+    }
+}
+
+// integral image of the raw 8-bit pixels, stored row after row in one buffer
+// with a zero first row and column. sums wrap around, which keeps them exact
+// for any region of fewer than 2^32 / 255 pixels
+#[derive(Debug)]
+pub struct FastIntegralImage {
+    stride: usize,
+    data: Vec<u32>,
+}
+
+impl FastIntegralImage {
+    pub fn build(image: &GrayImage) -> FastIntegralImage {
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let stride = width + 1;
+        let pixels: &[u8] = &**image;
+
+        let mut data = vec![0u32; stride * (height + 1)];
+
+        for y in 0..height {
+            let mut row_sum = 0u32;
+
+            for x in 0..width {
+                row_sum = row_sum.wrapping_add(pixels[y * width + x] as u32);
+                data[(y + 1) * stride + x + 1] = data[y * stride + x + 1].wrapping_add(row_sum);
+            }
+        }
+
+        FastIntegralImage {
+            stride: stride,
+            data: data,
+        }
+    }
+
+    // distance between two rows of the buffer, which compiled rectangles depend on
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    // position of the upper left corner of a window at column x and row y
+    pub fn origin(&self, x: usize, y: usize) -> usize {
+        y * self.stride + x
+    }
+
+    pub fn sum(&self, origin: usize, offsets: &RectOffsets) -> u32 {
+        self.data[origin + offsets.bottom_right]
+            .wrapping_sub(self.data[origin + offsets.bottom_left])
+            .wrapping_sub(self.data[origin + offsets.upper_right])
+            .wrapping_add(self.data[origin + offsets.upper_left])
+    }
+
+    pub fn sum_region(&self, rect: &Rect) -> u32 {
+        self.sum(0, &RectOffsets::new(rect, self.stride))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::GrayImage;
+    use rand::{Rng, SeedableRng, StdRng};
+    use geometry::Rect;
+    use super::FastIntegralImage;
+
+    #[test]
+    fn sum_region_matches_brute_force_on_random_images() {
+        let seed: &[_] = &[38, 38];
+        let mut rng: StdRng = SeedableRng::from_seed(seed);
+
+        for _ in 0..500 {
+            let width = rng.gen_range(1, 40);
+            let height = rng.gen_range(1, 40);
+            let pixels = (0..width * height).map(|_| rng.gen::<u8>()).collect();
+            let image = GrayImage::from_raw(width, height, pixels).unwrap();
+            let integral_image = FastIntegralImage::build(&image);
+
+            let x = rng.gen_range(0, width);
+            let y = rng.gen_range(0, height);
+            let rect = Rect::new(x as usize,
+                                 y as usize,
+                                 rng.gen_range(0, width - x + 1) as usize,
+                                 rng.gen_range(0, height - y + 1) as usize);
+
+            let mut expected = 0;
+            for py in rect.y..rect.bottom() {
+                for px in rect.x..rect.right() {
+                    expected += image.get_pixel(px as u32, py as u32).data[0] as u32;
+                }
+            }
+
+            assert_eq!(integral_image.sum_region(&rect), expected, "{:?}", rect);
+        }
+    }
+}
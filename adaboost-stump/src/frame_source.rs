@@ -0,0 +1,439 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use image::{self, DynamicImage, GrayImage, RgbImage};
+use load::{open_gray_image, to_gray_image};
+
+const IMAGE_EXTENSIONS: [&'static str; 8] = ["png", "jpg", "jpeg", "gif", "bmp", "pgm", "ppm",
+                                             "tif"];
+
+// `*` matches any run of characters and `?` a single one
+fn wildcard_match(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some(&b'*'), _) => {
+            wildcard_match(&pattern[1..], name) ||
+            (!name.is_empty() && wildcard_match(pattern, &name[1..]))
+        }
+        (Some(&b'?'), Some(_)) => wildcard_match(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) if p == n => wildcard_match(&pattern[1..], &name[1..]),
+        _ => false,
+    }
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map_or(false, |extension| IMAGE_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
+
+// images of a directory, or the files matching a wildcard in the last component
+fn list_images(spec: &str) -> Vec<PathBuf> {
+    let path = Path::new(spec);
+
+    let mut paths: Vec<PathBuf> = if path.is_dir() {
+        fs::read_dir(path)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.is_file() && is_image(path))
+            .collect()
+    } else {
+        let directory = match path.parent() {
+            Some(parent) if parent != Path::new("") => parent,
+            _ => Path::new("."),
+        };
+        let pattern = path.file_name().unwrap().to_string_lossy().into_owned();
+
+        fs::read_dir(directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| {
+                path.is_file() &&
+                wildcard_match(pattern.as_bytes(),
+                               path.file_name().unwrap().to_string_lossy().as_bytes())
+            })
+            .collect()
+    };
+
+    paths.sort();
+    paths
+}
+
+fn read_exact_or_eof<R: Read>(reader: &mut R, buffer: &mut [u8]) -> io::Result<bool> {
+    match reader.read_exact(buffer) {
+        Ok(()) => Ok(true),
+        Err(ref error) if error.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(error) => Err(error),
+    }
+}
+
+// YUV4MPEG2 stream, keeping only the luma plane of each frame
+pub struct Y4mReader<R> {
+    reader: R,
+    width: usize,
+    height: usize,
+    chroma_size: usize,
+}
+
+impl Y4mReader<BufReader<File>> {
+    pub fn open(path: &str) -> Y4mReader<BufReader<File>> {
+        Y4mReader::new(BufReader::new(File::open(path).expect("Unable to open video")), path)
+    }
+}
+
+impl<R: BufRead> Y4mReader<R> {
+    // `name` only shows up in the error messages
+    pub fn new(mut reader: R, name: &str) -> Y4mReader<R> {
+        let mut header = String::new();
+        reader.read_line(&mut header).expect("Unable to read Y4M header");
+
+        let mut tokens = header.split_whitespace();
+        if tokens.next() != Some("YUV4MPEG2") {
+            panic!("Not a Y4M file: {}", name);
+        }
+
+        let (mut width, mut height, mut colorspace) = (0, 0, "420".to_string());
+        for token in tokens {
+            let (tag, value) = token.split_at(1);
+            match tag {
+                "W" => width = value.parse().unwrap(),
+                "H" => height = value.parse().unwrap(),
+                "C" => colorspace = value.to_string(),
+                _ => {}
+            }
+        }
+
+        let (chroma_width, chroma_height) = ((width + 1) / 2, (height + 1) / 2);
+        let chroma_size = if colorspace.starts_with("mono") {
+            0
+        } else if colorspace.starts_with("444") {
+            2 * width * height
+        } else if colorspace.starts_with("422") {
+            2 * chroma_width * height
+        } else {
+            2 * chroma_width * chroma_height
+        };
+
+        Y4mReader {
+            reader: reader,
+            width: width,
+            height: height,
+            chroma_size: chroma_size,
+        }
+    }
+
+    fn read_frame(&mut self) -> io::Result<Option<GrayImage>> {
+        let mut frame_header = String::new();
+        if self.reader.read_line(&mut frame_header)? == 0 {
+            return Ok(None);
+        }
+
+        let mut luma = vec![0; self.width * self.height];
+        if !read_exact_or_eof(&mut self.reader, &mut luma)? {
+            return Ok(None);
+        }
+
+        let mut chroma = vec![0; self.chroma_size];
+        read_exact_or_eof(&mut self.reader, &mut chroma)?;
+
+        Ok(GrayImage::from_raw(self.width as u32, self.height as u32, luma))
+    }
+}
+
+// headerless 8-bit grayscale frames stored back to back
+pub struct RawReader<R> {
+    reader: R,
+    width: usize,
+    height: usize,
+}
+
+impl RawReader<BufReader<File>> {
+    pub fn open(path: &str, width: usize, height: usize) -> RawReader<BufReader<File>> {
+        RawReader::new(BufReader::new(File::open(path).expect("Unable to open video")),
+                       width,
+                       height)
+    }
+}
+
+impl<R: Read> RawReader<R> {
+    pub fn new(reader: R, width: usize, height: usize) -> RawReader<R> {
+        RawReader {
+            reader: reader,
+            width: width,
+            height: height,
+        }
+    }
+
+    fn read_frame(&mut self) -> io::Result<Option<GrayImage>> {
+        let mut luma = vec![0; self.width * self.height];
+        if !read_exact_or_eof(&mut self.reader, &mut luma)? {
+            return Ok(None);
+        }
+
+        Ok(GrayImage::from_raw(self.width as u32, self.height as u32, luma))
+    }
+}
+
+// RIFF AVI file whose video stream is motion JPEG
+pub struct AviReader<R> {
+    reader: R,
+}
+
+impl AviReader<BufReader<File>> {
+    pub fn open(path: &str) -> AviReader<BufReader<File>> {
+        AviReader::new(BufReader::new(File::open(path).expect("Unable to open video")), path)
+    }
+}
+
+impl<R: Read + Seek> AviReader<R> {
+    // `name` only shows up in the error messages
+    pub fn new(mut reader: R, name: &str) -> AviReader<R> {
+        let mut header = [0; 12];
+        reader.read_exact(&mut header).expect("Unable to read AVI header");
+        if &header[0..4] != b"RIFF" || &header[8..12] != b"AVI " {
+            panic!("Not an AVI file: {}", name);
+        }
+
+        AviReader { reader: reader }
+    }
+
+    // walks the chunks in file order: lists are entered rather than skipped,
+    // so the compressed frames (`##dc` or `##db`) come out in sequence. a
+    // frame that does not decode, such as a webcam JPEG without its huffman
+    // tables, comes out as its error so that the frames after it keep their
+    // numbers
+    fn read_frame(&mut self) -> io::Result<Option<image::ImageResult<DynamicImage>>> {
+        loop {
+            let mut chunk_header = [0; 8];
+            if !read_exact_or_eof(&mut self.reader, &mut chunk_header)? {
+                return Ok(None);
+            }
+
+            let id = &chunk_header[0..4];
+            let size = chunk_header[4..8]
+                .iter()
+                .rev()
+                .fold(0, |acc, &byte| (acc << 8) | byte as u64);
+
+            if id == b"LIST" || id == b"RIFF" {
+                // skip the list type and read its children
+                self.reader.seek(SeekFrom::Current(4))?;
+                continue;
+            }
+
+            let is_video = &id[2..4] == b"dc" || &id[2..4] == b"db";
+            if !is_video || size == 0 {
+                self.reader.seek(SeekFrom::Current((size + size % 2) as i64))?;
+                continue;
+            }
+
+            let mut data = vec![0; size as usize];
+            if !read_exact_or_eof(&mut self.reader, &mut data)? {
+                return Ok(None);
+            }
+            if size % 2 == 1 {
+                self.reader.seek(SeekFrom::Current(1))?;
+            }
+
+            return Ok(Some(image::load_from_memory(&data)));
+        }
+    }
+}
+
+// one picture of a source, with a name for the reports
+pub struct Frame {
+    pub name: String,
+    pub gray: GrayImage,
+    // kept for the skin filter when the source has colours
+    pub colour: Option<RgbImage>,
+    // why the frame could not be decoded, its pictures being empty then
+    pub error: Option<String>,
+}
+
+impl Frame {
+    fn gray(name: String, gray: GrayImage) -> Frame {
+        Frame {
+            name: name,
+            gray: gray,
+            colour: None,
+            error: None,
+        }
+    }
+
+    fn failed(name: String, error: String) -> Frame {
+        Frame {
+            name: name,
+            gray: GrayImage::new(0, 0),
+            colour: None,
+            error: Some(error),
+        }
+    }
+
+    fn decoded(name: String, img: DynamicImage) -> Frame {
+        let colour = match img {
+            DynamicImage::ImageLuma8(_) |
+            DynamicImage::ImageLumaA8(_) => None,
+            _ => Some(img.to_rgb()),
+        };
+
+        Frame {
+            name: name,
+            gray: to_gray_image(img),
+            colour: colour,
+            error: None,
+        }
+    }
+}
+
+// a sequence of frames: grayscale for Y4M (only its luma plane is read) and
+// raw videos, in colour when the images or the JPEGs of the AVI are
+pub enum FrameSource {
+    Images(Vec<PathBuf>, usize),
+    Y4m(Y4mReader<BufReader<File>>, usize),
+    Raw(RawReader<BufReader<File>>, usize),
+    Avi(AviReader<BufReader<File>>, usize),
+}
+
+impl FrameSource {
+    // picks the reader from the input: a directory or wildcard of images, or a
+    // .y4m, .avi or raw .gray file (the latter needs the frame size)
+    pub fn open(spec: &str, raw_size: Option<(usize, usize)>) -> FrameSource {
+        let lowercase = spec.to_lowercase();
+
+        if lowercase.ends_with(".y4m") {
+            FrameSource::Y4m(Y4mReader::open(spec), 0)
+        } else if lowercase.ends_with(".avi") {
+            FrameSource::Avi(AviReader::open(spec), 0)
+        } else if lowercase.ends_with(".gray") || lowercase.ends_with(".raw") {
+            let (width, height) = raw_size.expect("Raw video needs --width and --height");
+            FrameSource::Raw(RawReader::open(spec, width, height), 0)
+        } else {
+            FrameSource::Images(list_images(spec), 0)
+        }
+    }
+}
+
+impl Iterator for FrameSource {
+    type Item = Frame;
+
+    fn next(&mut self) -> Option<Frame> {
+        match *self {
+            FrameSource::Images(ref paths, ref mut index) => {
+                let path = paths.get(*index)?;
+                *index += 1;
+
+                let name = path.to_string_lossy().into_owned();
+                let is_pgm = path.extension().map_or(false, |extension| extension == "pgm");
+
+                if is_pgm {
+                    Some(Frame::gray(name, open_gray_image(path)))
+                } else {
+                    Some(Frame::decoded(name, image::open(path).unwrap()))
+                }
+            }
+            FrameSource::Y4m(ref mut reader, ref mut index) => {
+                let frame = reader.read_frame().expect("Unable to read frame")?;
+                *index += 1;
+
+                Some(Frame::gray(format!("frame {}", *index - 1), frame))
+            }
+            FrameSource::Raw(ref mut reader, ref mut index) => {
+                let frame = reader.read_frame().expect("Unable to read frame")?;
+                *index += 1;
+
+                Some(Frame::gray(format!("frame {}", *index - 1), frame))
+            }
+            FrameSource::Avi(ref mut reader, ref mut index) => {
+                let frame = reader.read_frame().expect("Unable to read frame")?;
+                *index += 1;
+
+                let name = format!("frame {}", *index - 1);
+                match frame {
+                    Ok(img) => Some(Frame::decoded(name, img)),
+                    Err(error) => Some(Frame::failed(name, error.to_string())),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use image::ColorType;
+    use image::jpeg::JPEGEncoder;
+
+    #[test]
+    fn y4m_frames_keep_their_luma() {
+        let mut bytes = b"YUV4MPEG2 W4 H2 F25:1 C420jpeg\n".to_vec();
+        for frame in 0..2 {
+            bytes.extend(b"FRAME\n".iter());
+            bytes.extend((0..8).map(|i| 10 * frame + i));
+            bytes.extend([128; 4].iter());
+        }
+
+        let mut reader = Y4mReader::new(Cursor::new(bytes), "test");
+
+        assert_eq!(reader.read_frame().unwrap().unwrap().into_raw(),
+                   vec![0, 1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(reader.read_frame().unwrap().unwrap().into_raw(),
+                   vec![10, 11, 12, 13, 14, 15, 16, 17]);
+        assert!(reader.read_frame().unwrap().is_none());
+    }
+
+    #[test]
+    fn raw_frames_stop_at_the_last_full_one() {
+        let mut reader = RawReader::new(Cursor::new((0..15).collect::<Vec<u8>>()), 3, 2);
+
+        assert_eq!(reader.read_frame().unwrap().unwrap().into_raw(), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(reader.read_frame().unwrap().unwrap().into_raw(),
+                   vec![6, 7, 8, 9, 10, 11]);
+        assert!(reader.read_frame().unwrap().is_none());
+    }
+
+    // an id, the little endian size and the data padded to an even length
+    fn chunk(id: &[u8], data: &[u8]) -> Vec<u8> {
+        let size = data.len() as u32;
+        let mut bytes = id.to_vec();
+        bytes.extend((0..4).map(|i| (size >> (8 * i)) as u8));
+        bytes.extend(data.iter());
+        if data.len() % 2 == 1 {
+            bytes.push(0);
+        }
+
+        bytes
+    }
+
+    fn list(kind: &[u8], children: Vec<Vec<u8>>) -> Vec<u8> {
+        let mut data = kind.to_vec();
+        for child in children {
+            data.extend(child);
+        }
+
+        chunk(b"LIST", &data)
+    }
+
+    #[test]
+    fn avi_frames_that_do_not_decode_keep_their_place() {
+        let mut jpeg = Vec::new();
+        JPEGEncoder::new(&mut jpeg).encode(&[90; 64], 8, 8, ColorType::Gray(8)).unwrap();
+
+        let movi = list(b"movi",
+                        vec![chunk(b"00dc", &jpeg),
+                             chunk(b"01wb", &[1, 2, 3]),
+                             chunk(b"00dc", &[0xff, 0xd8, 0xff, 0x00, 0x42]),
+                             chunk(b"00dc", &jpeg)]);
+        let mut riff = b"AVI ".to_vec();
+        riff.extend(list(b"hdrl", vec![chunk(b"avih", &[0; 56])]));
+        riff.extend(movi);
+
+        let mut reader = AviReader::new(Cursor::new(chunk(b"RIFF", &riff)), "test");
+
+        assert_eq!(reader.read_frame().unwrap().unwrap().unwrap().to_luma().dimensions(),
+                   (8, 8));
+        assert!(reader.read_frame().unwrap().unwrap().is_err());
+        assert!(reader.read_frame().unwrap().unwrap().is_ok());
+        assert!(reader.read_frame().unwrap().is_none());
+    }
+}
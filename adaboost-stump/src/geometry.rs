@@ -0,0 +1,300 @@
+// x runs along the columns of an image and y along its rows, so a matrix
+// holding an image is always indexed as [[y, x]]
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct Point {
+    pub x: usize,
+    pub y: usize,
+}
+
+impl Point {
+    pub fn new(x: usize, y: usize) -> Point {
+        Point { x: x, y: y }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct Size {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Size {
+    pub fn new(width: usize, height: usize) -> Size {
+        Size {
+            width: width,
+            height: height,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Rect {
+        Rect {
+            x: x,
+            y: y,
+            width: width,
+            height: height,
+        }
+    }
+
+    pub fn from_parts(origin: Point, size: Size) -> Rect {
+        Rect::new(origin.x, origin.y, size.width, size.height)
+    }
+
+    pub fn origin(&self) -> Point {
+        Point::new(self.x, self.y)
+    }
+
+    pub fn size(&self) -> Size {
+        Size::new(self.width, self.height)
+    }
+
+    // first column past the rectangle
+    pub fn right(&self) -> usize {
+        self.x + self.width
+    }
+
+    // first row past the rectangle
+    pub fn bottom(&self) -> usize {
+        self.y + self.height
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        point.x >= self.x && point.x < self.right() && point.y >= self.y &&
+        point.y < self.bottom()
+    }
+
+    pub fn offset(&self, by: Point) -> Rect {
+        Rect::new(self.x + by.x, self.y + by.y, self.width, self.height)
+    }
+
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.right() && other.x < self.right() && self.y < other.bottom() &&
+        other.y < self.bottom()
+    }
+
+    pub fn area(&self) -> usize {
+        self.width * self.height
+    }
+
+    pub fn intersection_area(&self, other: &Rect) -> usize {
+        let width = self.right().min(other.right()).saturating_sub(self.x.max(other.x));
+        let height = self.bottom().min(other.bottom()).saturating_sub(self.y.max(other.y));
+
+        width * height
+    }
+
+    // intersection over union
+    pub fn overlap(&self, other: &Rect) -> f64 {
+        let intersection = self.intersection_area(other);
+        let union = self.area() + other.area() - intersection;
+
+        if union == 0 {
+            0.0
+        } else {
+            intersection as f64 / union as f64
+        }
+    }
+}
+
+// a box of `width` x `height` centred on (cx, cy), turned clockwise by `angle`
+// degrees around its centre. coordinates are continuous: pixel (x, y) covers
+// [x, x + 1) x [y, y + 1)
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct RotatedRect {
+    pub cx: f64,
+    pub cy: f64,
+    pub width: f64,
+    pub height: f64,
+    pub angle: f64,
+}
+
+impl RotatedRect {
+    pub fn from_rect(rect: &Rect) -> RotatedRect {
+        RotatedRect {
+            cx: rect.x as f64 + rect.width as f64 / 2.0,
+            cy: rect.y as f64 + rect.height as f64 / 2.0,
+            width: rect.width as f64,
+            height: rect.height as f64,
+            angle: 0.0,
+        }
+    }
+
+    // upper left, upper right, bottom right and bottom left before the turn
+    pub fn corners(&self) -> Vec<(f64, f64)> {
+        let (sin, cos) = self.angle.to_radians().sin_cos();
+        let (half_width, half_height) = (self.width / 2.0, self.height / 2.0);
+
+        [(-half_width, -half_height),
+         (half_width, -half_height),
+         (half_width, half_height),
+         (-half_width, half_height)]
+            .iter()
+            .map(|&(x, y)| (self.cx + x * cos - y * sin, self.cy + x * sin + y * cos))
+            .collect()
+    }
+
+    // smallest upright rectangle holding the box, cut at the top and left
+    // borders of the picture
+    pub fn bounding_rect(&self) -> Rect {
+        let corners = self.corners();
+        let (left, top, right, bottom) = corners.iter().fold((corners[0].0,
+                                                              corners[0].1,
+                                                              corners[0].0,
+                                                              corners[0].1),
+                                                             |acc, &(x, y)| {
+            (acc.0.min(x), acc.1.min(y), acc.2.max(x), acc.3.max(y))
+        });
+
+        let (x, y) = (left.round().max(0.0) as usize, top.round().max(0.0) as usize);
+        let (right, bottom) = (right.round().max(0.0) as usize, bottom.round().max(0.0) as usize);
+
+        Rect::new(x, y, right.saturating_sub(x), bottom.saturating_sub(y))
+    }
+
+    // intersection over union of the two turned boxes
+    pub fn overlap(&self, other: &RotatedRect) -> f64 {
+        let intersection = polygon_area(&clip_polygon(&self.corners(), &other.corners()));
+        let union = self.width * self.height + other.width * other.height - intersection;
+
+        if union <= 0.0 {
+            0.0
+        } else {
+            intersection / union
+        }
+    }
+}
+
+// twice the signed area of the triangle o, a, b
+fn cross(o: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+}
+
+fn signed_area(polygon: &Vec<(f64, f64)>) -> f64 {
+    (0..polygon.len()).fold(0.0, |acc, i| {
+        let (a, b) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+        acc + a.0 * b.1 - b.0 * a.1
+    }) / 2.0
+}
+
+pub fn polygon_area(polygon: &Vec<(f64, f64)>) -> f64 {
+    signed_area(polygon).abs()
+}
+
+// part of `subject` inside the convex polygon `clip` (Sutherland-Hodgman)
+pub fn clip_polygon(subject: &Vec<(f64, f64)>, clip: &Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+    // inside is on the same side of every edge whichever way `clip` turns
+    let orientation = signed_area(clip).signum();
+    let mut output = subject.clone();
+
+    for i in 0..clip.len() {
+        if output.is_empty() {
+            break;
+        }
+
+        let (a, b) = (clip[i], clip[(i + 1) % clip.len()]);
+        let input = output;
+        output = Vec::new();
+
+        for j in 0..input.len() {
+            let (p, q) = (input[j], input[(j + 1) % input.len()]);
+            let (side_p, side_q) = (cross(a, b, p) * orientation, cross(a, b, q) * orientation);
+
+            if side_p >= 0.0 {
+                output.push(p);
+            }
+
+            if (side_p >= 0.0) != (side_q >= 0.0) {
+                let t = side_p / (side_p - side_q);
+                output.push((p.0 + t * (q.0 - p.0), p.1 + t * (q.1 - p.1)));
+            }
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    fn turned(cx: f64, cy: f64, width: f64, height: f64, angle: f64) -> RotatedRect {
+        RotatedRect {
+            cx: cx,
+            cy: cy,
+            width: width,
+            height: height,
+            angle: angle,
+        }
+    }
+
+    #[test]
+    fn identical_boxes_overlap_fully() {
+        for &angle in &[0.0, 30.0, 90.0, -135.0] {
+            let rect = turned(20.0, 15.0, 12.0, 8.0, angle);
+            assert!(close(rect.overlap(&rect), 1.0), "{} degrees", angle);
+        }
+    }
+
+    #[test]
+    fn disjoint_boxes_do_not_overlap() {
+        let a = turned(10.0, 10.0, 10.0, 10.0, 45.0);
+        let b = turned(30.0, 10.0, 10.0, 10.0, 45.0);
+
+        assert_eq!(a.overlap(&b), 0.0);
+        assert_eq!(b.overlap(&a), 0.0);
+    }
+
+    #[test]
+    fn upright_boxes_overlap_like_rects() {
+        let (a, b) = (Rect::new(0, 0, 10, 10), Rect::new(5, 0, 10, 10));
+        let overlap = RotatedRect::from_rect(&a).overlap(&RotatedRect::from_rect(&b));
+
+        assert!(close(overlap, a.overlap(&b)));
+        assert!(close(overlap, 1.0 / 3.0));
+    }
+
+    #[test]
+    fn a_box_turned_a_quarter_overlaps_its_square_middle() {
+        let upright = turned(20.0, 20.0, 20.0, 10.0, 0.0);
+        let quarter = turned(20.0, 20.0, 20.0, 10.0, 90.0);
+
+        // a 10x10 square in common out of 300
+        assert!(close(upright.overlap(&quarter), 1.0 / 3.0));
+    }
+
+    #[test]
+    fn clipping_does_not_depend_on_the_orientation_of_the_clip() {
+        let subject = turned(10.0, 10.0, 10.0, 10.0, 0.0).corners();
+        let clip = turned(15.0, 15.0, 10.0, 10.0, 0.0).corners();
+        let reversed: Vec<_> = clip.iter().rev().cloned().collect();
+
+        assert!(close(polygon_area(&clip_polygon(&subject, &clip)), 25.0));
+        assert!(close(polygon_area(&clip_polygon(&subject, &reversed)), 25.0));
+        assert!(clip_polygon(&subject, &turned(40.0, 40.0, 5.0, 5.0, 10.0).corners()).is_empty());
+    }
+
+    #[test]
+    fn bounding_rects_hold_the_turned_corners() {
+        assert_eq!(turned(15.0, 15.0, 10.0, 10.0, 0.0).bounding_rect(), Rect::new(10, 10, 10, 10));
+        // half diagonal of 7.07
+        assert_eq!(turned(15.0, 15.0, 10.0, 10.0, 45.0).bounding_rect(), Rect::new(8, 8, 14, 14));
+        assert_eq!(turned(15.0, 15.0, 20.0, 10.0, 90.0).bounding_rect(),
+                   Rect::new(10, 5, 10, 20));
+        // cut at the top and left borders
+        assert_eq!(turned(2.0, 2.0, 10.0, 10.0, 0.0).bounding_rect(), Rect::new(0, 0, 7, 7));
+    }
+}
@@ -1,7 +1,8 @@
 use integral_image::IntegralImage;
+use geometry::Rect;
 use shared::{MIN_FEATURE_HEIGHT, MIN_FEATURE_WIDTH, WINDOW_HEIGHT, WINDOW_WIDTH};
 
-#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
 enum HaarLikeFeatureType {
     TwoVertical, // two columns vertical
     TwoHorizontal, // two columns horizontal
@@ -10,12 +11,13 @@ enum HaarLikeFeatureType {
     FourCheckers, // four squares checkerboard
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct HaarLikeFeature {
     pub threshold: f64,
     pub weight: f64,
     pub polarity: f64,
     feature_type: HaarLikeFeatureType,
+    // bounding box inside the window: x is the column and y the row
     x: usize,
     y: usize,
     width: usize,
@@ -63,73 +65,51 @@ impl HaarLikeFeature {
     }
 
     fn get_score_two_vertical(&self, integral_image: &IntegralImage) -> f64 {
-        let left_box = integral_image.sum_region(self.x, self.y, self.width / 2, self.height);
+        let (x, y, w, h) = (self.x, self.y, self.width, self.height);
 
-        let right_box =
-            integral_image.sum_region(self.x + self.width / 2, self.y, self.width / 2, self.height);
+        let left_box = integral_image.sum_region(&Rect::new(x, y, w / 2, h));
+        let right_box = integral_image.sum_region(&Rect::new(x + w / 2, y, w / 2, h));
 
         left_box - right_box
     }
 
     fn get_score_two_horizontal(&self, integral_image: &IntegralImage) -> f64 {
-        let upper_box = integral_image.sum_region(self.x, self.y, self.width, self.height / 2);
+        let (x, y, w, h) = (self.x, self.y, self.width, self.height);
 
-        let lower_box = integral_image.sum_region(self.x,
-                                                  self.y + self.height / 2,
-                                                  self.width,
-                                                  self.height / 2);
+        let upper_box = integral_image.sum_region(&Rect::new(x, y, w, h / 2));
+        let lower_box = integral_image.sum_region(&Rect::new(x, y + h / 2, w, h / 2));
 
         upper_box - lower_box
     }
 
     fn get_score_three_horizontal(&self, integral_image: &IntegralImage) -> f64 {
-        let upper_box = integral_image.sum_region(self.x, self.y, self.width, self.height / 3);
+        let (x, y, w, h) = (self.x, self.y, self.width, self.height);
 
-        let mid_box = integral_image.sum_region(self.x,
-                                                self.y + self.height / 3,
-                                                self.width,
-                                                self.height / 3);
-
-        let lower_box = integral_image.sum_region(self.x,
-                                                  self.y + 2 * self.height / 3,
-                                                  self.width,
-                                                  self.height / 3);
+        let upper_box = integral_image.sum_region(&Rect::new(x, y, w, h / 3));
+        let mid_box = integral_image.sum_region(&Rect::new(x, y + h / 3, w, h / 3));
+        let lower_box = integral_image.sum_region(&Rect::new(x, y + 2 * h / 3, w, h / 3));
 
         upper_box - mid_box + lower_box
     }
 
     fn get_score_three_vertical(&self, integral_image: &IntegralImage) -> f64 {
-        let left_box = integral_image.sum_region(self.x, self.y, self.width / 3, self.height);
-
-        let mid_box =
-            integral_image.sum_region(self.x + self.width / 3, self.y, self.width / 3, self.height);
+        let (x, y, w, h) = (self.x, self.y, self.width, self.height);
 
-        let right_box = integral_image.sum_region(self.x + 2 * self.width / 3,
-                                                  self.y,
-                                                  self.width / 3,
-                                                  self.height);
+        let left_box = integral_image.sum_region(&Rect::new(x, y, w / 3, h));
+        let mid_box = integral_image.sum_region(&Rect::new(x + w / 3, y, w / 3, h));
+        let right_box = integral_image.sum_region(&Rect::new(x + 2 * w / 3, y, w / 3, h));
 
         left_box - mid_box + right_box
     }
 
     fn get_score_four_checkers(&self, integral_image: &IntegralImage) -> f64 {
-        let upper_left_box =
-            integral_image.sum_region(self.x, self.y, self.width / 2, self.height / 2);
-
-        let upper_right_box = integral_image.sum_region(self.x + self.width / 2,
-                                                        self.y,
-                                                        self.width / 2,
-                                                        self.height / 2);
+        let (x, y, w, h) = (self.x, self.y, self.width, self.height);
 
-        let bottom_right_box = integral_image.sum_region(self.x,
-                                                         self.y + self.height / 2,
-                                                         self.width / 2,
-                                                         self.height / 2);
-
-        let bottom_left_box = integral_image.sum_region(self.x + self.width / 2,
-                                                        self.y + self.height / 2,
-                                                        self.width / 2,
-                                                        self.height / 2);
+        let upper_left_box = integral_image.sum_region(&Rect::new(x, y, w / 2, h / 2));
+        let upper_right_box = integral_image.sum_region(&Rect::new(x + w / 2, y, w / 2, h / 2));
+        let bottom_left_box = integral_image.sum_region(&Rect::new(x, y + h / 2, w / 2, h / 2));
+        let bottom_right_box =
+            integral_image.sum_region(&Rect::new(x + w / 2, y + h / 2, w / 2, h / 2));
 
         upper_left_box - upper_right_box - bottom_left_box + bottom_right_box
     }
@@ -146,6 +126,51 @@ impl HaarLikeFeature {
         }
     }
 
+    // same type at the same place, whatever the threshold and polarity
+    pub fn same_boxes(&self, other: &HaarLikeFeature) -> bool {
+        self.feature_type == other.feature_type && self.x == other.x && self.y == other.y &&
+        self.width == other.width && self.height == other.height
+    }
+
+    // boxes of the feature with the sign they enter the score with
+    pub fn boxes(&self) -> Vec<(Rect, f64)> {
+        let (x, y, w, h) = (self.x, self.y, self.width, self.height);
+
+        match self.feature_type {
+            HaarLikeFeatureType::TwoVertical => {
+                vec![(Rect::new(x, y, w / 2, h), 1.0), (Rect::new(x + w / 2, y, w / 2, h), -1.0)]
+            }
+            HaarLikeFeatureType::TwoHorizontal => {
+                vec![(Rect::new(x, y, w, h / 2), 1.0), (Rect::new(x, y + h / 2, w, h / 2), -1.0)]
+            }
+            HaarLikeFeatureType::ThreeHorizontal => {
+                vec![(Rect::new(x, y, w, h / 3), 1.0),
+                     (Rect::new(x, y + h / 3, w, h / 3), -1.0),
+                     (Rect::new(x, y + 2 * h / 3, w, h / 3), 1.0)]
+            }
+            HaarLikeFeatureType::ThreeVertical => {
+                vec![(Rect::new(x, y, w / 3, h), 1.0),
+                     (Rect::new(x + w / 3, y, w / 3, h), -1.0),
+                     (Rect::new(x + 2 * w / 3, y, w / 3, h), 1.0)]
+            }
+            HaarLikeFeatureType::FourCheckers => {
+                vec![(Rect::new(x, y, w / 2, h / 2), 1.0),
+                     (Rect::new(x + w / 2, y, w / 2, h / 2), -1.0),
+                     (Rect::new(x, y + h / 2, w / 2, h / 2), -1.0),
+                     (Rect::new(x + w / 2, y + h / 2, w / 2, h / 2), 1.0)]
+            }
+        }
+    }
+
+    // boxes of the feature with their sign, flipped by the polarity so that
+    // positive boxes are the ones brighter on faces
+    pub fn rectangles(&self) -> Vec<(Rect, f64)> {
+        self.boxes()
+            .into_iter()
+            .map(|(rect, sign)| (rect, sign * self.polarity))
+            .collect()
+    }
+
     pub fn predict(&self, integral_image: &IntegralImage) -> f64 {
         let score = self.get_score(integral_image);
         self.weight * self.polarity * (score - self.threshold).signum()
@@ -0,0 +1,195 @@
+use std::f64::consts::PI;
+use rulinalg::matrix::BaseMatrix;
+use integral_image::IntegralImage;
+use detector::Detector;
+use linear_svm::LinearSvm;
+use shared::ImageData;
+
+pub const CELL_SIZE: usize = 6;
+pub const BLOCK_SIZE: usize = 2; // cells per block side
+pub const NUM_BINS: usize = 9;
+
+// clipping value of the L2-Hys block normalization
+const HYS_CLIP: f64 = 0.2;
+
+fn get_pixel(image: &ImageData, row: isize, col: isize) -> f64 {
+    // replicate the border
+    let row = row.max(0).min(image.rows() as isize - 1) as usize;
+    let col = col.max(0).min(image.cols() as isize - 1) as usize;
+
+    image[[row, col]]
+}
+
+fn normalize_block(block: &mut Vec<f64>) {
+    let norm = (block.iter().fold(0.0, |acc, v| acc + v * v) + 1e-10).sqrt();
+    for value in block.iter_mut() {
+        *value = (*value / norm).min(HYS_CLIP);
+    }
+
+    let norm = (block.iter().fold(0.0, |acc, v| acc + v * v) + 1e-10).sqrt();
+    for value in block.iter_mut() {
+        *value /= norm;
+    }
+}
+
+// gradient magnitudes of each cell by unsigned orientation, cells row by row
+fn cell_histograms(image: &ImageData) -> Vec<f64> {
+    let cells_down = image.rows() / CELL_SIZE;
+    let cells_across = image.cols() / CELL_SIZE;
+    let bin_width = PI / (NUM_BINS as f64);
+
+    let mut histograms = vec![0.0; cells_down * cells_across * NUM_BINS];
+
+    for row in 0..(cells_down * CELL_SIZE) {
+        for col in 0..(cells_across * CELL_SIZE) {
+            let (r, c) = (row as isize, col as isize);
+            let dx = get_pixel(image, r, c + 1) - get_pixel(image, r, c - 1);
+            let dy = get_pixel(image, r + 1, c) - get_pixel(image, r - 1, c);
+
+            let magnitude = dx.hypot(dy);
+            let mut angle = dy.atan2(dx);
+            if angle < 0.0 {
+                angle += PI;
+            }
+
+            // split the vote between the two nearest bins
+            let position = angle / bin_width - 0.5;
+            let lower = position.floor();
+            let fraction = position - lower;
+            let lower_bin = ((lower as isize + NUM_BINS as isize) as usize) % NUM_BINS;
+            let upper_bin = (lower_bin + 1) % NUM_BINS;
+
+            let cell = (row / CELL_SIZE) * cells_across + col / CELL_SIZE;
+            histograms[cell * NUM_BINS + lower_bin] += magnitude * (1.0 - fraction);
+            histograms[cell * NUM_BINS + upper_bin] += magnitude * fraction;
+        }
+    }
+
+    histograms
+}
+
+// histogram of oriented gradients with unsigned orientations and overlapping blocks
+pub fn compute_descriptor(image: &ImageData) -> Vec<f64> {
+    let cells_down = image.rows() / CELL_SIZE;
+    let cells_across = image.cols() / CELL_SIZE;
+    let histograms = cell_histograms(image);
+
+    let mut descriptor = Vec::new();
+
+    for block_row in 0..(cells_down + 1 - BLOCK_SIZE) {
+        for block_col in 0..(cells_across + 1 - BLOCK_SIZE) {
+            let mut block = Vec::with_capacity(BLOCK_SIZE * BLOCK_SIZE * NUM_BINS);
+
+            for i in 0..BLOCK_SIZE {
+                for j in 0..BLOCK_SIZE {
+                    let cell = (block_row + i) * cells_across + block_col + j;
+                    block.extend_from_slice(&histograms[(cell * NUM_BINS)..((cell + 1) * NUM_BINS)]);
+                }
+            }
+
+            normalize_block(&mut block);
+            descriptor.extend(block);
+        }
+    }
+
+    descriptor
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct HogDetector {
+    svm: LinearSvm,
+}
+
+impl HogDetector {
+    pub fn new(svm: LinearSvm) -> HogDetector {
+        HogDetector { svm: svm }
+    }
+}
+
+impl Detector for HogDetector {
+    fn predict_window(&self, image_data: &ImageData, _: &IntegralImage) -> bool {
+        self.svm.decision_function(&compute_descriptor(image_data)) > 0.0
+    }
+
+    fn score_window(&self, image_data: &ImageData, _: &IntegralImage) -> Option<f64> {
+        let margin = self.svm.decision_function(&compute_descriptor(image_data));
+
+        if margin > 0.0 { Some(margin) } else { None }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rulinalg::matrix::Matrix;
+
+    fn norm(values: &[f64]) -> f64 {
+        values.iter().fold(0.0, |acc, v| acc + v * v).sqrt()
+    }
+
+    // 3x3 cells, dark before the 9th column or row and bright from it
+    fn edge(vertical: bool) -> ImageData {
+        Matrix::from_fn(18, 18, |col, row| {
+            let position = if vertical { col } else { row };
+            if position < 9 { 0.0 } else { 1.0 }
+        })
+    }
+
+    #[test]
+    fn edges_vote_for_their_orientation() {
+        // a vertical edge has horizontal gradients, at the border between the
+        // first and the last bin; the two columns of the edge fall in the
+        // middle column of cells
+        let histograms = cell_histograms(&edge(true));
+        for cell in 0..9 {
+            let bins = &histograms[cell * NUM_BINS..(cell + 1) * NUM_BINS];
+            let expected = if cell % 3 == 1 { 6.0 } else { 0.0 };
+
+            assert!((bins[0] - expected).abs() < 1e-9, "cell {}: {:?}", cell, bins);
+            assert!((bins[NUM_BINS - 1] - expected).abs() < 1e-9, "cell {}: {:?}", cell, bins);
+            assert!(bins[1..NUM_BINS - 1].iter().all(|&vote| vote.abs() < 1e-9));
+        }
+
+        // a horizontal edge has vertical gradients, the centre of the middle bin
+        let histograms = cell_histograms(&edge(false));
+        for cell in 0..9 {
+            let bins = &histograms[cell * NUM_BINS..(cell + 1) * NUM_BINS];
+            let expected = if cell / 3 == 1 { 12.0 } else { 0.0 };
+
+            assert!((bins[NUM_BINS / 2] - expected).abs() < 1e-9, "cell {}: {:?}", cell, bins);
+            assert!((norm(bins) - expected).abs() < 1e-9, "cell {}: {:?}", cell, bins);
+        }
+    }
+
+    #[test]
+    fn blocks_are_clipped_and_of_unit_length() {
+        // a dominant value is clipped down to the level of the others
+        let mut block = vec![3.0, 4.0];
+        normalize_block(&mut block);
+        assert!((block[0] - block[1]).abs() < 1e-9);
+        assert!((norm(&block) - 1.0).abs() < 1e-9);
+
+        let mut block: Vec<f64> =
+            (0..BLOCK_SIZE * BLOCK_SIZE * NUM_BINS).map(|i| i as f64).collect();
+        normalize_block(&mut block);
+        assert!((norm(&block) - 1.0).abs() < 1e-9);
+        assert!(block.windows(2).all(|pair| pair[0] <= pair[1]));
+
+        // nothing to normalize
+        let mut block = vec![0.0; 4];
+        normalize_block(&mut block);
+        assert!(block.iter().all(|&value| value == 0.0));
+    }
+
+    #[test]
+    fn descriptors_hold_one_normalized_histogram_per_block() {
+        let descriptor = compute_descriptor(&edge(true));
+        let block_length = BLOCK_SIZE * BLOCK_SIZE * NUM_BINS;
+
+        // 3x3 cells give 2x2 overlapping blocks
+        assert_eq!(descriptor.len(), 4 * block_length);
+        for block in descriptor.chunks(block_length) {
+            assert!((norm(block) - 1.0).abs() < 1e-9);
+        }
+    }
+}
@@ -0,0 +1,395 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::io;
+use std::net::TcpStream;
+use std::time::Duration;
+use serde_json;
+
+// uploads above this are refused before reading them
+const MAX_BODY_SIZE: usize = 32 * 1024 * 1024;
+// longer request lines and header sections are refused while reading them
+const MAX_REQUEST_LINE: usize = 8 * 1024;
+const MAX_HEADER_SIZE: usize = 16 * 1024;
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    if haystack.len() < needle.len() {
+        return None;
+    }
+
+    (from..(haystack.len() - needle.len() + 1)).find(|&i| &haystack[i..i + needle.len()] == needle)
+}
+
+fn malformed<E>(_: E) -> Response {
+    Response::error(400, "Malformed request")
+}
+
+// a client too slow to send its request gets a timeout rather than a 400
+fn read_error(error: io::Error) -> Response {
+    match error.kind() {
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => {
+            Response::error(408, "Request timed out")
+        }
+        _ => malformed(error),
+    }
+}
+
+// a line of at most `limit` bytes, end of line included; None when it is longer
+fn read_line<R: BufRead>(reader: &mut R, limit: usize) -> Result<Option<String>, Response> {
+    let mut line = Vec::new();
+    reader.by_ref().take(limit as u64 + 1).read_until(b'\n', &mut line).map_err(read_error)?;
+
+    if line.len() > limit {
+        return Ok(None);
+    }
+
+    String::from_utf8(line).map(Some).map_err(malformed)
+}
+
+// a tcp stream read through a buffer; 100-continue and the response are
+// written to the same stream. reads and writes give up after `timeout`, so a
+// client that stops sending does not hold its thread forever
+pub struct Connection {
+    reader: BufReader<TcpStream>,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream, timeout: Duration) -> io::Result<Connection> {
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+
+        Ok(Connection { reader: BufReader::new(stream) })
+    }
+}
+
+impl Read for Connection {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buffer)
+    }
+}
+
+impl BufRead for Connection {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.reader.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.reader.consume(amount)
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        self.reader.get_mut().write(buffer)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.reader.get_mut().flush()
+    }
+}
+
+// just enough of HTTP/1.1 for one request per connection with a sized body
+#[derive(Debug)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    query: Vec<(String, String)>,
+    headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    // errors come back as the response to send
+    pub fn read<S: BufRead + Write>(stream: &mut S) -> Result<Request, Response> {
+        let request_line = read_line(stream, MAX_REQUEST_LINE)?
+            .ok_or(Response::error(414, "Request line too long"))?;
+
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().ok_or(()).map_err(malformed)?.to_string();
+        let target = parts.next().ok_or(()).map_err(malformed)?;
+
+        let (path, query_string) = match target.find('?') {
+            Some(i) => (&target[..i], &target[i + 1..]),
+            None => (target, ""),
+        };
+
+        let query = query_string.split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| match pair.find('=') {
+                Some(i) => (pair[..i].to_string(), pair[i + 1..].to_string()),
+                None => (pair.to_string(), String::new()),
+            })
+            .collect();
+
+        let mut headers = Vec::new();
+        let mut header_size = 0;
+        loop {
+            let line = read_line(stream, MAX_HEADER_SIZE - header_size)?
+                .ok_or(Response::error(431, "Headers too large"))?;
+            header_size += line.len();
+
+            let line = line.trim_right();
+            if line.is_empty() {
+                break;
+            }
+
+            if let Some(i) = line.find(':') {
+                headers.push((line[..i].trim().to_lowercase(), line[i + 1..].trim().to_string()));
+            }
+        }
+
+        let mut request = Request {
+            method: method,
+            path: path.to_string(),
+            query: query,
+            headers: headers,
+            body: Vec::new(),
+        };
+
+        if request.header("transfer-encoding").is_some() {
+            return Err(Response::error(411, "Send the upload with a Content-Length"));
+        }
+
+        let length = match request.header("content-length") {
+            Some(length) => length.parse().map_err(malformed)?,
+            None => 0,
+        };
+
+        if length > MAX_BODY_SIZE {
+            return Err(Response::error(413, "Upload too large"));
+        }
+
+        // curl waits for this before sending large uploads
+        let expect = request.header("expect").unwrap_or("").to_lowercase();
+        if expect == "100-continue" {
+            stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").map_err(read_error)?;
+        }
+
+        request.body = vec![0; length];
+        stream.read_exact(&mut request.body).map_err(read_error)?;
+
+        Ok(request)
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|&&(ref key, _)| key == name)
+            .map(|&(_, ref value)| value.as_str())
+    }
+
+    pub fn query_param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|&&(ref key, _)| key == name)
+            .map(|&(_, ref value)| value.as_str())
+    }
+
+    // the raw body, or the first part of a multipart/form-data upload
+    pub fn upload(&self) -> Option<&[u8]> {
+        let content_type = self.header("content-type").unwrap_or("");
+
+        if !content_type.starts_with("multipart/form-data") {
+            return Some(&self.body);
+        }
+
+        let boundary = content_type.split(';')
+            .map(|parameter| parameter.trim())
+            .find(|parameter| parameter.starts_with("boundary="))
+            .map(|parameter| parameter["boundary=".len()..].trim_matches('"'))?;
+
+        let delimiter = format!("--{}", boundary).into_bytes();
+        let first = find(&self.body, &delimiter, 0)?;
+        let start = find(&self.body, b"\r\n\r\n", first)? + 4;
+
+        let mut closing = b"\r\n".to_vec();
+        closing.extend(delimiter);
+        let end = find(&self.body, &closing, start)?;
+
+        Some(&self.body[start..end])
+    }
+}
+
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+impl Response {
+    // json body
+    pub fn ok(body: String) -> Response {
+        Response {
+            status: 200,
+            body: body,
+        }
+    }
+
+    pub fn error(status: u16, message: &str) -> Response {
+        Response {
+            status: status,
+            body: format!("{{\"error\":{}}}", serde_json::to_string(message).unwrap()),
+        }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            408 => "Request Timeout",
+            411 => "Length Required",
+            413 => "Payload Too Large",
+            414 => "URI Too Long",
+            415 => "Unsupported Media Type",
+            431 => "Request Header Fields Too Large",
+            503 => "Service Unavailable",
+            _ => "Internal Server Error",
+        }
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write!(writer,
+               "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+                Connection: close\r\n\r\n{}",
+               self.status,
+               self.reason(),
+               self.body.len(),
+               self.body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // the bytes a client sends and those the server writes back
+    struct Exchange {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Exchange {
+        fn new(input: &[u8]) -> Exchange {
+            Exchange {
+                input: Cursor::new(input.to_vec()),
+                output: Vec::new(),
+            }
+        }
+    }
+
+    impl Read for Exchange {
+        fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buffer)
+        }
+    }
+
+    impl BufRead for Exchange {
+        fn fill_buf(&mut self) -> io::Result<&[u8]> {
+            self.input.fill_buf()
+        }
+
+        fn consume(&mut self, amount: usize) {
+            self.input.consume(amount)
+        }
+    }
+
+    impl Write for Exchange {
+        fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+            self.output.write(buffer)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn read(input: &[u8]) -> Result<Request, Response> {
+        Request::read(&mut Exchange::new(input))
+    }
+
+    #[test]
+    fn query_and_headers_are_parsed() {
+        let request = read(b"POST /detect?min_size=40&skin&max_size= HTTP/1.1\r\n\
+                             Host: localhost\r\n\
+                             X-Custom :  some value \r\n\r\n")
+            .unwrap();
+
+        assert_eq!((request.method.as_str(), request.path.as_str()), ("POST", "/detect"));
+        assert_eq!(request.query_param("min_size"), Some("40"));
+        assert_eq!(request.query_param("skin"), Some(""));
+        assert_eq!(request.query_param("max_size"), Some(""));
+        assert_eq!(request.query_param("other"), None);
+        assert_eq!(request.header("host"), Some("localhost"));
+        assert_eq!(request.header("x-custom"), Some("some value"));
+        assert!(request.body.is_empty());
+    }
+
+    #[test]
+    fn the_body_is_read_up_to_its_length() {
+        let request = read(b"POST /detect HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello world")
+            .unwrap();
+
+        assert_eq!(request.body, b"hello".to_vec());
+        assert_eq!(request.upload(), Some(&b"hello"[..]));
+
+        assert_eq!(read(b"POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nhey").unwrap_err().status,
+                   400);
+        assert_eq!(read(b"POST / HTTP/1.1\r\nContent-Length: x\r\n\r\n").unwrap_err().status,
+                   400);
+        assert_eq!(read(b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n")
+                       .unwrap_err()
+                       .status,
+                   411);
+        assert_eq!(read(b"POST / HTTP/1.1\r\nContent-Length: 999999999\r\n\r\n")
+                       .unwrap_err()
+                       .status,
+                   413);
+    }
+
+    #[test]
+    fn continue_is_sent_before_the_body() {
+        let mut exchange = Exchange::new(b"POST /detect HTTP/1.1\r\nExpect: 100-continue\r\n\
+                                           Content-Length: 2\r\n\r\nok");
+        let request = Request::read(&mut exchange).unwrap();
+
+        assert_eq!(request.body, b"ok".to_vec());
+        assert_eq!(exchange.output, b"HTTP/1.1 100 Continue\r\n\r\n".to_vec());
+    }
+
+    #[test]
+    fn the_first_part_of_a_multipart_upload_is_the_image() {
+        let body = b"--xyz\r\nContent-Disposition: form-data; name=\"image\"; \
+                     filename=\"a.png\"\r\nContent-Type: image/png\r\n\r\n\
+                     PNGDATA\r\n--xyz\r\nContent-Disposition: form-data; name=\"x\"\r\n\r\n\
+                     1\r\n--xyz--\r\n";
+        let mut raw = format!("POST /detect HTTP/1.1\r\n\
+                               Content-Type: multipart/form-data; boundary=\"xyz\"\r\n\
+                               Content-Length: {}\r\n\r\n",
+                              body.len())
+            .into_bytes();
+        raw.extend(body.iter());
+
+        assert_eq!(read(&raw).unwrap().upload(), Some(&b"PNGDATA"[..]));
+    }
+
+    #[test]
+    fn oversized_requests_are_refused() {
+        let mut long_line = b"GET /".to_vec();
+        long_line.extend(vec![b'a'; MAX_REQUEST_LINE]);
+        long_line.extend(b" HTTP/1.1\r\n\r\n".iter());
+        assert_eq!(read(&long_line).unwrap_err().status, 414);
+
+        let mut many_headers = b"GET / HTTP/1.1\r\n".to_vec();
+        for i in 0..MAX_HEADER_SIZE / 16 {
+            many_headers.extend(format!("X-Header-{:05}: 1\r\n", i).into_bytes());
+        }
+        many_headers.extend(b"\r\n".iter());
+        assert_eq!(read(&many_headers).unwrap_err().status, 431);
+
+        // a header line that never ends
+        let mut endless = b"GET / HTTP/1.1\r\nX-Endless: ".to_vec();
+        endless.extend(vec![b'a'; 2 * MAX_HEADER_SIZE]);
+        assert_eq!(read(&endless).unwrap_err().status, 431);
+    }
+}
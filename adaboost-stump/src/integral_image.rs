@@ -1,4 +1,5 @@
 use rulinalg::matrix::{BaseMatrix, Matrix};
+use geometry::Rect;
 use shared::ImageData;
 
 #[derive(Debug)]
@@ -48,13 +49,76 @@ impl IntegralImage {
         IntegralImage { data: mat }
     }
 
-    pub fn sum_region(&self, x: usize, y: usize, width: usize, height: usize) -> f64 {
-        // bounding box
-        let upper_left = self.data[[x, y]];
-        let upper_right = self.data[[x + width, y]];
-        let bottom_left = self.data[[x, y + height]];
-        let bottom_right = self.data[[x + width, y + height]];
+    pub fn sum_region(&self, rect: &Rect) -> f64 {
+        // bounding box, indexed [[row, column]]
+        let upper_left = self.data[[rect.y, rect.x]];
+        let upper_right = self.data[[rect.y, rect.right()]];
+        let bottom_left = self.data[[rect.bottom(), rect.x]];
+        let bottom_right = self.data[[rect.bottom(), rect.right()]];
 
         bottom_right - bottom_left - upper_right + upper_left
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng, StdRng};
+    use rulinalg::matrix::Matrix;
+    use geometry::Rect;
+    use super::IntegralImage;
+
+    fn brute_force_sum(image: &Matrix<f64>, rect: &Rect) -> f64 {
+        let mut sum = 0.0;
+        for y in rect.y..rect.bottom() {
+            for x in rect.x..rect.right() {
+                sum += image[[y, x]];
+            }
+        }
+
+        sum
+    }
+
+    #[test]
+    fn sum_region_matches_brute_force_on_random_images() {
+        let seed: &[_] = &[19, 19];
+        let mut rng: StdRng = SeedableRng::from_seed(seed);
+
+        for _ in 0..500 {
+            let rows = rng.gen_range(1, 40);
+            let cols = rng.gen_range(1, 40);
+            let image = Matrix::from_fn(rows, cols, |_, _| rng.gen::<f64>());
+            let integral_image = IntegralImage::build(&image);
+
+            let x = rng.gen_range(0, cols);
+            let y = rng.gen_range(0, rows);
+            let width = rng.gen_range(0, cols - x + 1);
+            let height = rng.gen_range(0, rows - y + 1);
+            let rect = Rect::new(x, y, width, height);
+
+            let expected = brute_force_sum(&image, &rect);
+            let actual = integral_image.sum_region(&rect);
+
+            assert!((expected - actual).abs() < 1e-9,
+                    "{:?} on a {}x{} image: expected {}, got {}",
+                    rect,
+                    cols,
+                    rows,
+                    expected,
+                    actual);
+        }
+    }
+
+    #[test]
+    fn sum_region_tells_columns_from_rows() {
+        // a single bright pixel at column 3 of row 1 of a wide image
+        // rulinalg hands the column to the closure first
+        let image = Matrix::from_fn(2, 5, |col, row| {
+            if row == 1 && col == 3 { 1.0 } else { 0.0 }
+        });
+        let integral_image = IntegralImage::build(&image);
+
+        assert_eq!(integral_image.sum_region(&Rect::new(3, 1, 1, 1)), 1.0);
+        assert_eq!(integral_image.sum_region(&Rect::new(1, 0, 1, 2)), 0.0);
+        assert_eq!(integral_image.sum_region(&Rect::new(0, 0, 5, 2)), 1.0);
+    }
+}
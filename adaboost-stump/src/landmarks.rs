@@ -0,0 +1,351 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use image::GrayImage;
+use rand::Rng;
+use serde_json;
+use annotations::{FaceFeature, NUM_LANDMARKS};
+use geometry::Rect;
+use warp::sample_bilinear;
+
+pub const NUM_STAGES: usize = 10;
+pub const NUM_FERNS: usize = 50;
+pub const FERN_DEPTH: usize = 4;
+pub const NUM_POINTS: usize = 400;
+
+// how far from its landmark a shape-indexed pixel may be, relative to the box
+const POINT_RADIUS: f64 = 0.15;
+
+// fraction of the mean residual of a bin applied by each fern
+const LEARNING_RATE: f64 = 0.1;
+
+// a shape is the landmarks flattened as x0, y0, x1, y1... in box coordinates,
+// where (0, 0) is the upper left corner of the box and (1, 1) the lower right
+type Shape = Vec<f64>;
+
+fn to_box_coordinates(feature: &FaceFeature, face_box: &(f64, f64, f64)) -> Shape {
+    let &(x, y, size) = face_box;
+
+    feature.to_vec()
+        .iter()
+        .flat_map(|&(px, py)| vec![(px - x) / size, (py - y) / size].into_iter())
+        .collect()
+}
+
+fn from_box_coordinates(shape: &Shape, face_box: &(f64, f64, f64)) -> FaceFeature {
+    let &(x, y, size) = face_box;
+
+    let points = (0..NUM_LANDMARKS)
+        .map(|i| (x + shape[2 * i] * size, y + shape[2 * i + 1] * size))
+        .collect();
+
+    FaceFeature::from_vec(&points)
+}
+
+// square the landmarks of a detection are expressed in, as (x, y, side)
+fn face_box(rect: &Rect) -> (f64, f64, f64) {
+    (rect.x as f64, rect.y as f64, rect.width.max(rect.height) as f64)
+}
+
+// pixel at an offset from one of the landmarks of the current shape
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+struct ShapeIndexedPoint {
+    landmark: usize,
+    dx: f64,
+    dy: f64,
+}
+
+impl ShapeIndexedPoint {
+    fn intensity(&self, img: &GrayImage, shape: &Shape, face_box: &(f64, f64, f64)) -> f64 {
+        let &(x, y, size) = face_box;
+        let px = x + (shape[2 * self.landmark] + self.dx) * size;
+        let py = y + (shape[2 * self.landmark + 1] + self.dy) * size;
+
+        sample_bilinear(img, px, py)
+    }
+}
+
+// compares pixel pairs against thresholds and outputs the shape increment of
+// the bin the comparisons fall in
+#[derive(Serialize, Deserialize, Debug)]
+struct Fern {
+    pairs: Vec<(usize, usize)>,
+    thresholds: Vec<f64>,
+    outputs: Vec<Shape>,
+}
+
+impl Fern {
+    fn get_bin(&self, intensities: &Vec<f64>) -> usize {
+        self.pairs
+            .iter()
+            .zip(self.thresholds.iter())
+            .enumerate()
+            .fold(0, |bin, (bit, (&(a, b), &threshold))| {
+                if intensities[a] - intensities[b] > threshold {
+                    bin | (1 << bit)
+                } else {
+                    bin
+                }
+            })
+    }
+
+    fn apply(&self, intensities: &Vec<f64>, shape: &mut Shape) {
+        let output = &self.outputs[self.get_bin(intensities)];
+        for (value, delta) in shape.iter_mut().zip(output.iter()) {
+            *value += *delta;
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct RegressionStage {
+    points: Vec<ShapeIndexedPoint>,
+    ferns: Vec<Fern>,
+}
+
+impl RegressionStage {
+    fn intensities(&self, img: &GrayImage, shape: &Shape, face_box: &(f64, f64, f64)) -> Vec<f64> {
+        self.points.iter().map(|point| point.intensity(img, shape, face_box)).collect()
+    }
+}
+
+// cascaded shape regression with random ferns over shape-indexed pixel differences
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LandmarkRegressor {
+    mean_shape: Shape,
+    stages: Vec<RegressionStage>,
+}
+
+impl LandmarkRegressor {
+    // each sample is an image, the box the face was found in and its landmarks
+    pub fn train<R: Rng>(samples: &Vec<(&GrayImage, Rect, FaceFeature)>,
+                         rng: &mut R)
+                         -> LandmarkRegressor {
+        assert!(!samples.is_empty(), "Unable to train landmarks without samples");
+
+        let face_boxes: Vec<_> = samples.iter().map(|&(_, ref rect, _)| face_box(rect)).collect();
+
+        let targets: Vec<Shape> = samples.iter()
+            .zip(face_boxes.iter())
+            .map(|(&(_, _, ref feature), face_box)| to_box_coordinates(feature, face_box))
+            .collect();
+
+        let mut mean_shape = vec![0.0; 2 * NUM_LANDMARKS];
+        for target in targets.iter() {
+            for (value, t) in mean_shape.iter_mut().zip(target.iter()) {
+                *value += t / (targets.len() as f64);
+            }
+        }
+
+        let mut shapes: Vec<Shape> = targets.iter().map(|_| mean_shape.clone()).collect();
+        let mut stages = Vec::new();
+
+        for s in 0..NUM_STAGES {
+            let points: Vec<_> = (0..NUM_POINTS)
+                .map(|_| {
+                    ShapeIndexedPoint {
+                        landmark: rng.gen_range(0, NUM_LANDMARKS),
+                        dx: rng.gen_range(-POINT_RADIUS, POINT_RADIUS),
+                        dy: rng.gen_range(-POINT_RADIUS, POINT_RADIUS),
+                    }
+                })
+                .collect();
+
+            let mut stage = RegressionStage {
+                points: points,
+                ferns: Vec::new(),
+            };
+
+            // pixels are indexed by the shapes at the start of the stage
+            let intensities: Vec<Vec<f64>> = samples.iter()
+                .zip(shapes.iter())
+                .zip(face_boxes.iter())
+                .map(|((&(img, _, _), shape), face_box)| stage.intensities(img, shape, face_box))
+                .collect();
+
+            for _ in 0..NUM_FERNS {
+                let pairs: Vec<_> = (0..FERN_DEPTH)
+                    .map(|_| (rng.gen_range(0, NUM_POINTS), rng.gen_range(0, NUM_POINTS)))
+                    .collect();
+
+                // thresholds are differences seen on random samples
+                let thresholds: Vec<_> = pairs.iter()
+                    .map(|&(a, b)| {
+                        let ref sample = intensities[rng.gen_range(0, intensities.len())];
+                        sample[a] - sample[b]
+                    })
+                    .collect();
+
+                let mut fern = Fern {
+                    pairs: pairs,
+                    thresholds: thresholds,
+                    outputs: vec![vec![0.0; 2 * NUM_LANDMARKS]; 1 << FERN_DEPTH],
+                };
+
+                let bins: Vec<_> = intensities.iter().map(|i| fern.get_bin(i)).collect();
+                let mut counts = vec![0; 1 << FERN_DEPTH];
+
+                for ((&bin, target), shape) in bins.iter().zip(targets.iter()).zip(shapes.iter()) {
+                    counts[bin] += 1;
+                    for (k, output) in fern.outputs[bin].iter_mut().enumerate() {
+                        *output += target[k] - shape[k];
+                    }
+                }
+
+                for (output, &count) in fern.outputs.iter_mut().zip(counts.iter()) {
+                    for value in output.iter_mut() {
+                        *value = if count > 0 {
+                            LEARNING_RATE * *value / (count as f64)
+                        } else {
+                            0.0
+                        };
+                    }
+                }
+
+                for (intensity, shape) in intensities.iter().zip(shapes.iter_mut()) {
+                    fern.apply(intensity, shape);
+                }
+
+                stage.ferns.push(fern);
+            }
+
+            stages.push(stage);
+            println!("Finished stage: {}", s + 1);
+        }
+
+        LandmarkRegressor {
+            mean_shape: mean_shape,
+            stages: stages,
+        }
+    }
+
+    // landmarks of the face found inside a detection box
+    pub fn predict(&self, img: &GrayImage, rect: &Rect) -> FaceFeature {
+        let face_box = face_box(rect);
+        let mut shape = self.mean_shape.clone();
+
+        for stage in self.stages.iter() {
+            let intensities = stage.intensities(img, &shape, &face_box);
+
+            for fern in stage.ferns.iter() {
+                fern.apply(&intensities, &mut shape);
+            }
+        }
+
+        from_box_coordinates(&shape, &face_box)
+    }
+
+    pub fn load(path: &str) -> LandmarkRegressor {
+        let mut model_raw = String::new();
+        let mut f = File::open(path).expect("Unable to open file");
+        f.read_to_string(&mut model_raw).expect("Unable to read string");
+
+        serde_json::from_str(&model_raw).unwrap()
+    }
+
+    pub fn save(&self, path: &str) {
+        let serialized = serde_json::to_string(self).unwrap();
+
+        let mut f = File::create(path).expect("Unable to create file");
+        f.write_all(serialized.as_bytes()).expect("Unable to write data");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Luma};
+    use rand::{SeedableRng, StdRng};
+
+    // landmarks in box coordinates that the synthetic faces are shifted around
+    const TEMPLATE: [f64; 12] = [0.3, 0.35, 0.7, 0.35, 0.5, 0.55, 0.35, 0.75, 0.5, 0.78, 0.65,
+                                 0.75];
+
+    // a bright 48x48 picture with a dark spot on each landmark of a face framed
+    // by (8, 8, 32) and moved by up to 4 pixels
+    fn shifted_face<R: Rng>(rng: &mut R) -> (GrayImage, FaceFeature) {
+        let (dx, dy) = (rng.gen_range(-4.0, 4.0), rng.gen_range(-4.0, 4.0));
+        let points: Vec<_> = (0..NUM_LANDMARKS)
+            .map(|i| (8.0 + 32.0 * TEMPLATE[2 * i] + dx, 8.0 + 32.0 * TEMPLATE[2 * i + 1] + dy))
+            .collect();
+
+        let img = ImageBuffer::from_fn(48, 48, |x, y| {
+            let distance = points.iter()
+                .map(|&(px, py)| (x as f64 - px).hypot(y as f64 - py))
+                .fold(::std::f64::INFINITY, f64::min);
+
+            Luma([(200.0 * (distance / 4.0).min(1.0)) as u8 + 40])
+        });
+
+        (img, FaceFeature::from_vec(&points))
+    }
+
+    fn mean_error(predictions: &Vec<FaceFeature>, truths: &Vec<FaceFeature>) -> f64 {
+        let error = predictions.iter()
+            .zip(truths.iter())
+            .flat_map(|(predicted, truth)| predicted.to_vec().into_iter().zip(truth.to_vec()))
+            .fold(0.0, |acc, (p, t)| acc + (p.0 - t.0).hypot(p.1 - t.1));
+
+        error / (predictions.len() * NUM_LANDMARKS) as f64
+    }
+
+    #[test]
+    fn box_coordinates_map_back_to_the_image() {
+        let feature = FaceFeature::from_vec(&vec![(12.0, 20.0), (28.0, 19.0), (20.0, 27.0),
+                                                  (14.0, 34.0), (20.0, 35.0), (26.0, 33.0)]);
+        let face_box = (4.0, 8.0, 32.0);
+
+        let shape = to_box_coordinates(&feature, &face_box);
+        assert_eq!(shape[0..4].to_vec(), vec![0.25, 0.375, 0.75, 0.34375]);
+
+        let back = from_box_coordinates(&shape, &face_box);
+        for (p, q) in back.to_vec().iter().zip(feature.to_vec().iter()) {
+            assert!((p.0 - q.0).abs() < 1e-9 && (p.1 - q.1).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn each_comparison_sets_its_own_bit() {
+        let fern = Fern {
+            pairs: vec![(0, 1), (1, 2), (2, 0)],
+            thresholds: vec![0.0, 5.0, 100.0],
+            outputs: vec![vec![0.0; 2 * NUM_LANDMARKS]; 8],
+        };
+
+        // bit i is set when the i-th difference is strictly above its threshold
+        assert_eq!(fern.get_bin(&vec![10.0, 20.0, 30.0]), 0b000);
+        assert_eq!(fern.get_bin(&vec![200.0, 0.0, 300.0]), 0b001);
+        assert_eq!(fern.get_bin(&vec![0.0, 50.0, 40.0]), 0b010);
+        assert_eq!(fern.get_bin(&vec![30.0, 20.0, 10.0]), 0b011);
+        assert_eq!(fern.get_bin(&vec![0.0, 0.0, 200.0]), 0b100);
+    }
+
+    #[test]
+    fn training_lowers_the_residual_on_held_out_faces() {
+        let mut rng: StdRng = SeedableRng::from_seed(&[35][..]);
+        let faces: Vec<_> = (0..120).map(|_| shifted_face(&mut rng)).collect();
+        let rect = Rect::new(8, 8, 32, 32);
+
+        let samples: Vec<_> = faces[..100].iter().map(|&(ref img, f)| (img, rect, f)).collect();
+        let regressor = LandmarkRegressor::train(&samples, &mut rng);
+
+        let held_out: Vec<_> = faces[100..].iter().map(|&(_, f)| f).collect();
+        let predictions: Vec<_> = faces[100..]
+            .iter()
+            .map(|&(ref img, _)| regressor.predict(img, &rect))
+            .collect();
+        let mean_shape = from_box_coordinates(&regressor.mean_shape, &face_box(&rect));
+
+        let before = mean_error(&vec![mean_shape; held_out.len()], &held_out);
+        let after = mean_error(&predictions, &held_out);
+        assert!(after < 0.5 * before,
+                "error went from {} to {} pixels",
+                before,
+                after);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unable to train landmarks without samples")]
+    fn training_needs_samples() {
+        LandmarkRegressor::train(&Vec::new(), &mut StdRng::new().unwrap());
+    }
+}
@@ -0,0 +1,107 @@
+use integral_image::IntegralImage;
+use geometry::Rect;
+use shared::{WINDOW_HEIGHT, WINDOW_WIDTH};
+
+pub const NUM_LBP_CODES: usize = 256;
+
+// neighbour blocks in clockwise order starting at the upper left corner,
+// as (column, row) offsets inside the 3x3 grid
+const NEIGHBOURS: [(usize, usize); 8] =
+    [(0, 0), (1, 0), (2, 0), (2, 1), (2, 2), (1, 2), (0, 2), (0, 1)];
+
+// multi-block local binary pattern: a 3x3 grid of equally sized blocks
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct LbpFeature {
+    x: usize,
+    y: usize,
+    block_width: usize,
+    block_height: usize,
+}
+
+impl LbpFeature {
+    pub fn generate_all_features() -> Vec<LbpFeature> {
+        let mut feature_hypotheses = Vec::new();
+
+        for block_height in 1..(WINDOW_HEIGHT / 3 + 1) {
+            for block_width in 1..(WINDOW_WIDTH / 3 + 1) {
+                for y in 0..(WINDOW_HEIGHT - 3 * block_height + 1) {
+                    for x in 0..(WINDOW_WIDTH - 3 * block_width + 1) {
+                        feature_hypotheses.push(LbpFeature {
+                            x: x,
+                            y: y,
+                            block_width: block_width,
+                            block_height: block_height,
+                        });
+                    }
+                }
+            }
+        }
+
+        feature_hypotheses
+    }
+
+    fn block(&self, column: usize, row: usize) -> Rect {
+        Rect::new(self.x + column * self.block_width,
+                  self.y + row * self.block_height,
+                  self.block_width,
+                  self.block_height)
+    }
+
+    fn sum_block(&self, integral_image: &IntegralImage, column: usize, row: usize) -> f64 {
+        integral_image.sum_region(&self.block(column, row))
+    }
+
+    // blocks of the 3x3 grid with their sign, the centre being negative and last
+    pub fn rectangles(&self) -> Vec<(Rect, f64)> {
+        let mut rectangles: Vec<_> = NEIGHBOURS.iter()
+            .map(|&(column, row)| (self.block(column, row), 1.0))
+            .collect();
+
+        rectangles.push((self.block(1, 1), -1.0));
+
+        rectangles
+    }
+
+    pub fn get_code(&self, integral_image: &IntegralImage) -> usize {
+        let center = self.sum_block(integral_image, 1, 1);
+
+        NEIGHBOURS.iter().enumerate().fold(0, |code, (bit, &(column, row))| {
+            if self.sum_block(integral_image, column, row) >= center {
+                code | (1 << bit)
+            } else {
+                code
+            }
+        })
+    }
+}
+
+// categorical weak learner: one vote per lbp code
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LbpClassifier {
+    pub weight: f64,
+    feature: LbpFeature,
+    table: Vec<f64>,
+}
+
+impl LbpClassifier {
+    pub fn new(feature: LbpFeature, table: Vec<f64>) -> LbpClassifier {
+        LbpClassifier {
+            weight: 1.0,
+            feature: feature,
+            table: table,
+        }
+    }
+
+    pub fn feature(&self) -> &LbpFeature {
+        &self.feature
+    }
+
+    // vote of each code before the weight is applied
+    pub fn table(&self) -> &Vec<f64> {
+        &self.table
+    }
+
+    pub fn predict(&self, integral_image: &IntegralImage) -> f64 {
+        self.weight * self.table[self.feature.get_code(integral_image)]
+    }
+}
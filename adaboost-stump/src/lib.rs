@@ -0,0 +1,34 @@
+// the detector as a library, for the C API in capi.rs and the tests in tests/;
+// the binaries declare the modules they use themselves
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_json;
+extern crate image;
+extern crate rulinalg;
+extern crate rand;
+
+pub mod shared;
+pub mod geometry;
+mod integral_image;
+pub mod haar_like_feature;
+mod decision_tree;
+mod lbp_feature;
+mod weak_classifier;
+pub mod prediction_ensemble;
+pub mod soft_cascade;
+pub mod detector;
+mod fast_integral_image;
+pub mod compiled_cascade;
+mod linear_svm;
+mod hog;
+mod model;
+pub mod detection_params;
+mod warp;
+mod prefilter;
+pub mod scanner;
+mod annotations;
+pub mod boosting;
+pub mod synthetic;
+mod args;
+pub mod capi;
@@ -0,0 +1,83 @@
+use rand::{thread_rng, Rng};
+use shared::Label;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LinearSvm {
+    weights: Vec<f64>,
+    bias: f64,
+}
+
+impl LinearSvm {
+    // pegasos: stochastic sub-gradient descent on the regularized hinge loss
+    pub fn train(samples: &Vec<(Vec<f64>, Label)>, lambda: f64, num_epochs: usize) -> LinearSvm {
+        let dimension = samples[0].0.len();
+        let mut svm = LinearSvm {
+            weights: vec![0.0; dimension],
+            bias: 0.0,
+        };
+
+        let mut order: Vec<usize> = (0..samples.len()).collect();
+        let mut t = 0;
+
+        for epoch in 0..num_epochs {
+            thread_rng().shuffle(order.as_mut_slice());
+
+            for &i in order.iter() {
+                t += 1;
+                let eta = 1.0 / (lambda * (t as f64));
+                let (ref x, y) = samples[i];
+                let margin = y * svm.decision_function(x);
+
+                // the bias is regularized like a constant extra feature
+                for w in svm.weights.iter_mut() {
+                    *w *= 1.0 - eta * lambda;
+                }
+                svm.bias *= 1.0 - eta * lambda;
+
+                if margin < 1.0 {
+                    for (w, xi) in svm.weights.iter_mut().zip(x.iter()) {
+                        *w += eta * y * xi;
+                    }
+                    svm.bias += eta * y;
+                }
+            }
+
+            println!("Finished epoch: {}", epoch + 1);
+        }
+
+        svm
+    }
+
+    pub fn decision_function(&self, x: &[f64]) -> f64 {
+        self.weights.iter().zip(x.iter()).fold(self.bias, |acc, (w, xi)| acc + w * xi)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, StdRng};
+
+    #[test]
+    fn separable_points_are_separated() {
+        let seed: &[_] = &[28];
+        let mut rng: StdRng = SeedableRng::from_seed(seed);
+
+        // points on either side of the line x + 2y = 1, at least 0.2 away
+        let mut samples = Vec::new();
+        while samples.len() < 200 {
+            let (x, y) = (rng.gen_range(-2.0, 2.0), rng.gen_range(-2.0, 2.0));
+            let side: f64 = x + 2.0 * y - 1.0;
+
+            if side.abs() >= 0.2 * 5.0f64.sqrt() {
+                samples.push((vec![x, y], side.signum()));
+            }
+        }
+
+        let svm = LinearSvm::train(&samples, 0.001, 50);
+
+        for &(ref x, label) in samples.iter() {
+            assert!(label * svm.decision_function(x) > 0.0, "{:?} on the wrong side", x);
+        }
+    }
+}
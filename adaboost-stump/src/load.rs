@@ -1,13 +1,20 @@
 use std::fs;
-use shared::{DataPoint, ImageData};
+use std::path::Path;
+use shared::{DataPoint, ImageData, Label, WINDOW_HEIGHT, WINDOW_WIDTH};
 use rulinalg::matrix::Matrix;
-use image::{DynamicImage, Pixel, GrayImage};
+use image::{self, ConvertBuffer, DynamicImage, FilterType, GrayImage, Pixel};
+use image::imageops;
 use integral_image::IntegralImage;
+use geometry::Rect;
+use manifest::{read_manifest, select_split};
+use pgm::read_pgm;
+use synthetic::synthetic_dataset;
+use args;
 
 type PreprocessedImage = GrayImage;
 
-fn get_luminosity_from_image(img: &PreprocessedImage, i: u32, j: u32) -> f64 {
-    let channels = img.get_pixel(i, j).channels();
+fn get_luminosity_from_image(img: &PreprocessedImage, x: u32, y: u32) -> f64 {
+    let channels = img.get_pixel(x, y).channels();
 
     channels[0] as f64
 }
@@ -18,10 +25,10 @@ fn get_luminosity_matrix(img: &PreprocessedImage) -> ImageData {
 
     let mut max = 1.0;
 
-    for i in 0..height {
-        for j in 0..width {
-            let index = [i as usize, j as usize];
-            let value = get_luminosity_from_image(img, i, j);
+    for y in 0..height {
+        for x in 0..width {
+            let index = [y as usize, x as usize];
+            let value = get_luminosity_from_image(img, x, y);
 
             mat[index] = value;
 
@@ -31,50 +38,155 @@ fn get_luminosity_matrix(img: &PreprocessedImage) -> ImageData {
         }
     }
 
-    for i in 0..height {
-        for j in 0..width {
-            let index = [i as usize, j as usize];
-            let value = mat[index] / max;
+    mat / max
+}
 
-            mat[index] = value;
-        }
+pub fn to_gray_image(img: DynamicImage) -> GrayImage {
+    match img {
+        DynamicImage::ImageLuma8(gray_image) => gray_image,
+        DynamicImage::ImageLumaA8(gray_alpha_image) => gray_alpha_image.convert(),
+        DynamicImage::ImageRgb8(rgb_image) => rgb_image.convert(),
+        DynamicImage::ImageRgba8(rgba_image) => rgba_image.convert(),
     }
+}
+
+// pgm files are decoded natively, everything else goes through the image crate
+pub fn open_gray_image<P: AsRef<Path>>(path: P) -> GrayImage {
+    let path = path.as_ref();
+    let is_pgm = path.extension().map_or(false, |extension| extension == "pgm");
 
-    mat
+    if is_pgm {
+        return read_pgm(path)
+            .unwrap_or_else(|error| panic!("Unable to read {}: {}", path.display(), error));
+    }
+
+    to_gray_image(image::open(path).unwrap())
+}
+
+fn crop_image(img: &GrayImage, rect: &Rect) -> GrayImage {
+    GrayImage::from_fn(rect.width as u32, rect.height as u32, |x, y| {
+        *img.get_pixel(rect.x as u32 + x, rect.y as u32 + y)
+    })
 }
 
-pub fn get_training_data() -> (Vec<DataPoint>, usize, usize) {
-    let faces_paths: Vec<_> =
-        fs::read_dir("./data/trainset/faces").unwrap().map(|path| (path, 1.0)).collect();
+fn build_data_point(gray_image: &PreprocessedImage, label: Label) -> DataPoint {
+    let image_data = get_luminosity_matrix(gray_image);
+    let integral_image = IntegralImage::build(&image_data);
+
+    DataPoint {
+        image_data: image_data,
+        integral_image: integral_image,
+        label: label,
+    }
+}
+
+// loads the `faces` and `non-faces` folders of a dataset directory
+pub fn get_data(directory: &str) -> (Vec<DataPoint>, usize, usize) {
+    let faces_paths: Vec<_> = fs::read_dir(format!("{}/faces", directory))
+        .unwrap()
+        .map(|path| (path, 1.0))
+        .collect();
     let num_faces = faces_paths.len();
 
-    let non_faces_paths: Vec<_> =
-        fs::read_dir("./data/trainset/non-faces").unwrap().map(|path| (path, -1.0)).collect();
+    let non_faces_paths: Vec<_> = fs::read_dir(format!("{}/non-faces", directory))
+        .unwrap()
+        .map(|path| (path, -1.0))
+        .collect();
     let num_non_faces = non_faces_paths.len();
 
     let paths = faces_paths.into_iter().chain(non_faces_paths.into_iter());
 
-    let mut training_data: Vec<DataPoint> = paths.map(|(path, label)| {
-            extern crate image;
-
+    let training_data: Vec<DataPoint> = paths.map(|(path, label)| {
             let img_path = path.unwrap().path();
+            let gray_image = open_gray_image(&img_path);
+
+            build_data_point(&gray_image, label)
+        })
+        .collect();
+
+    (training_data, num_faces, num_non_faces)
+}
+
+// loads the entries of a manifest, keeping only those of the given split
+pub fn get_manifest_data(manifest_path: &str, split: Option<&str>) -> (Vec<DataPoint>, usize, usize) {
+    let entries = select_split(read_manifest(manifest_path), split);
+
+    let num_faces = entries.iter().filter(|entry| entry.label > 0.0).count();
+    let num_non_faces = entries.len() - num_faces;
 
-            let gray_image = match image::open(img_path.clone()).unwrap() {
-                DynamicImage::ImageLuma8(gray_image) => gray_image,
-                _ => unreachable!(),
-            };
+    let data: Vec<DataPoint> = entries.iter()
+        .map(|entry| {
+            let mut gray_image = open_gray_image(&entry.path);
 
-            let image_data = get_luminosity_matrix(&gray_image);
+            if let Some(ref crop) = entry.crop {
+                let (width, height) = gray_image.dimensions();
+                assert!(crop.right() <= width as usize && crop.bottom() <= height as usize,
+                        "Manifest line {}: crop {:?} does not fit the {}x{} image {}",
+                        entry.line,
+                        crop,
+                        width,
+                        height,
+                        entry.path);
 
-            let integral_image = IntegralImage::build(&image_data);
+                gray_image = crop_image(&gray_image, crop);
+            }
 
-            DataPoint {
-                image_data: image_data,
-                integral_image: integral_image,
-                label: label,
+            if gray_image.dimensions() != (WINDOW_WIDTH as u32, WINDOW_HEIGHT as u32) {
+                gray_image = imageops::resize(&gray_image,
+                                              WINDOW_WIDTH as u32,
+                                              WINDOW_HEIGHT as u32,
+                                              FilterType::Triangle);
             }
+
+            build_data_point(&gray_image, entry.label)
         })
         .collect();
 
-    (training_data, num_faces, num_non_faces)
+    (data, num_faces, num_non_faces)
+}
+
+// `--synthetic N` generates N faces and N non-faces instead, from `--seed`
+// (0 when training, 1 otherwise, so that evaluations see other windows);
+// `--manifest` and `--split` take precedence over the dataset directory
+pub fn get_data_from_args(default_directory: &str,
+                          default_split: &str)
+                          -> (Vec<DataPoint>, usize, usize) {
+    if let Some(num_samples) = args::get_option("synthetic") {
+        let default_seed = if default_split == "train" { 0 } else { 1 };
+        let seed = args::get_option("seed").unwrap_or(default_seed);
+
+        return synthetic_dataset(seed, num_samples, num_samples);
+    }
+
+    match args::get_option::<String>("manifest") {
+        Some(manifest_path) => {
+            let split = args::get_option("split").unwrap_or(default_split.to_string());
+
+            if split == "all" {
+                get_manifest_data(&manifest_path, None)
+            } else {
+                get_manifest_data(&manifest_path, Some(&split))
+            }
+        }
+        None => {
+            let directory = args::get_option("dataset").unwrap_or(default_directory.to_string());
+            get_data(&directory)
+        }
+    }
+}
+
+// windows held out from those of get_data_from_args: the `split` rows of
+// `--manifest`, synthetic windows from the seed after `--seed`, or the dataset
+// in `directory`
+pub fn get_split_from_args(directory: &str, split: &str) -> (Vec<DataPoint>, usize, usize) {
+    if let Some(num_samples) = args::get_option("synthetic") {
+        let seed: usize = args::get_option("seed").unwrap_or(0);
+
+        return synthetic_dataset(seed + 1, num_samples, num_samples);
+    }
+
+    match args::get_option::<String>("manifest") {
+        Some(manifest_path) => get_manifest_data(&manifest_path, Some(split)),
+        None => get_data(directory),
+    }
 }
@@ -0,0 +1,195 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use serde_json;
+use geometry::Rect;
+use shared::Label;
+
+// one sample of a dataset: the image, its label, the box to crop out of it
+// and the split it belongs to (train, validation, test...)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ManifestEntry {
+    pub path: String,
+    pub label: Label,
+    #[serde(default)]
+    pub crop: Option<Rect>,
+    #[serde(default)]
+    pub split: Option<String>,
+    // line of the csv file, or position in the json list, counted from 1
+    #[serde(skip_serializing, skip_deserializing)]
+    pub line: usize,
+}
+
+fn parse_label(token: &str, line: usize) -> Label {
+    match token {
+        "1" | "+1" | "face" => 1.0,
+        "-1" | "0" | "non-face" => -1.0,
+        _ => panic!("Manifest line {}: invalid label {}", line, token),
+    }
+}
+
+fn parse_coordinate(token: &str, name: &str, line: usize) -> usize {
+    token.parse().unwrap_or_else(|_| panic!("Manifest line {}: invalid {} {}", line, name, token))
+}
+
+// csv with a `path,label,x,y,width,height,split` header; the crop box and the
+// split may be left empty
+fn parse_csv(raw: &str) -> Vec<ManifestEntry> {
+    let mut lines = raw.lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+        .filter(|&(_, line)| !line.trim().is_empty());
+
+    let header: Vec<String> = lines.next()
+        .expect("Empty manifest")
+        .1
+        .split(',')
+        .map(|column| column.trim().to_string())
+        .collect();
+
+    let column = |name: &str| header.iter().position(|column| column == name);
+    let (path, label) = (column("path").expect("Manifest needs a path column"),
+                         column("label").expect("Manifest needs a label column"));
+    let crop = (column("x"), column("y"), column("width"), column("height"));
+    let split = column("split");
+
+    lines.map(|(number, line)| {
+            let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+            let field = |index: Option<usize>| {
+                index.and_then(|i| fields.get(i).cloned()).filter(|value| !value.is_empty())
+            };
+
+            let crop = match (field(crop.0), field(crop.1), field(crop.2), field(crop.3)) {
+                (Some(x), Some(y), Some(width), Some(height)) => {
+                    Some(Rect::new(parse_coordinate(x, "x", number),
+                                   parse_coordinate(y, "y", number),
+                                   parse_coordinate(width, "width", number),
+                                   parse_coordinate(height, "height", number)))
+                }
+                _ => None,
+            };
+
+            ManifestEntry {
+                path: field(Some(path))
+                    .unwrap_or_else(|| panic!("Manifest line {}: no path", number))
+                    .to_string(),
+                label: parse_label(field(Some(label)).unwrap_or(""), number),
+                crop: crop,
+                split: field(split).map(|split| split.to_string()),
+                line: number,
+            }
+        })
+        .collect()
+}
+
+// reads a json or csv manifest; relative paths are resolved against its folder
+pub fn read_manifest(manifest_path: &str) -> Vec<ManifestEntry> {
+    let mut raw = String::new();
+    let mut f = File::open(manifest_path).expect("Unable to open manifest");
+    f.read_to_string(&mut raw).expect("Unable to read manifest");
+
+    let mut entries: Vec<ManifestEntry> = if manifest_path.ends_with(".json") {
+        serde_json::from_str(&raw).unwrap()
+    } else {
+        parse_csv(&raw)
+    };
+
+    let is_json = manifest_path.ends_with(".json");
+    let directory = Path::new(manifest_path).parent().unwrap_or(Path::new("."));
+    for (i, entry) in entries.iter_mut().enumerate() {
+        if is_json {
+            entry.line = i + 1;
+        }
+        entry.path = directory.join(&entry.path).to_string_lossy().into_owned();
+    }
+
+    entries
+}
+
+// the entries of `split`, or all of them without a split to pick. a manifest
+// without any split is used whole; one that has some needs one on every row,
+// rather than the rows without being left out of every split
+pub fn select_split(entries: Vec<ManifestEntry>, split: Option<&str>) -> Vec<ManifestEntry> {
+    let split = match split {
+        Some(split) => split,
+        None => return entries,
+    };
+
+    if entries.iter().all(|entry| entry.split.is_none()) {
+        return entries;
+    }
+
+    if let Some(entry) = entries.iter().find(|entry| entry.split.is_none()) {
+        panic!("Manifest line {}: {} has no split while other rows have one",
+               entry.line,
+               entry.path);
+    }
+
+    entries.into_iter()
+        .filter(|entry| entry.split.as_ref().map(|s| s.as_str()) == Some(split))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_columns_are_found_by_name() {
+        let entries = parse_csv("label, path,split,x,y,width,height\n\
+                                 face,a.pgm,train,1,2,3,4\n\
+                                 \n\
+                                 -1,b.pgm,,,,,\n");
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].path.as_str(), entries[0].label), ("a.pgm", 1.0));
+        assert_eq!(entries[0].crop, Some(Rect::new(1, 2, 3, 4)));
+        assert_eq!(entries[0].split, Some("train".to_string()));
+        assert_eq!((entries[1].label, entries[1].crop, entries[1].split.clone()),
+                   (-1.0, None, None));
+        assert_eq!((entries[0].line, entries[1].line), (2, 4));
+    }
+
+    #[test]
+    #[should_panic(expected = "Manifest line 2: invalid label maybe")]
+    fn unknown_labels_are_reported() {
+        parse_csv("path,label\na.pgm,maybe\n");
+    }
+
+    #[test]
+    #[should_panic(expected = "Manifest line 3: invalid width -4")]
+    fn invalid_crops_are_reported_with_their_line() {
+        parse_csv("path,label,x,y,width,height\na.pgm,1,0,0,4,4\nb.pgm,1,0,0,-4,4\n");
+    }
+
+    #[test]
+    #[should_panic(expected = "Manifest line 2: no path")]
+    fn short_rows_are_reported_with_their_line() {
+        parse_csv("label,path\n1\n");
+    }
+
+    fn names(entries: &Vec<ManifestEntry>) -> Vec<&str> {
+        entries.iter().map(|entry| entry.path.as_str()).collect()
+    }
+
+    #[test]
+    fn splits_pick_their_rows() {
+        let entries = parse_csv("path,label,split\na,1,train\nb,-1,test\nc,-1,train\n");
+
+        assert_eq!(names(&select_split(entries.clone(), Some("train"))), vec!["a", "c"]);
+        assert_eq!(names(&select_split(entries.clone(), Some("validation"))),
+                   Vec::<&str>::new());
+        assert_eq!(names(&select_split(entries, None)), vec!["a", "b", "c"]);
+
+        // without splits every split is the whole manifest
+        let entries = parse_csv("path,label\na,1\nb,-1\n");
+        assert_eq!(names(&select_split(entries, Some("train"))), vec!["a", "b"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Manifest line 3: b has no split while other rows have one")]
+    fn rows_missing_a_split_are_reported() {
+        let entries = parse_csv("path,label,split\na,1,train\nb,-1,\n");
+        select_split(entries, Some("train"));
+    }
+}
@@ -0,0 +1,71 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use serde_json;
+use integral_image::IntegralImage;
+use detector::Detector;
+use compiled_cascade::{CompiledCascade, WindowLayout};
+use prediction_ensemble::PredictionEnsemble;
+use soft_cascade::SoftCascade;
+use hog::HogDetector;
+use shared::ImageData;
+
+// every kind of detector the model file can hold
+#[derive(Serialize, Deserialize, Debug)]
+pub enum Model {
+    Cascade(PredictionEnsemble),
+    SoftCascade(SoftCascade),
+    Hog(HogDetector),
+}
+
+impl Model {
+    pub fn load(path: &str) -> Model {
+        let mut model_raw = String::new();
+        let mut f = File::open(path).expect("Unable to open file");
+        f.read_to_string(&mut model_raw).expect("Unable to read string");
+
+        serde_json::from_str(&model_raw).unwrap()
+    }
+
+    // for callers that cannot panic: the reason comes back instead
+    pub fn try_load(path: &str) -> Result<Model, String> {
+        let mut model_raw = String::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut model_raw))
+            .map_err(|error| format!("Unable to read {}: {}", path, error))?;
+
+        serde_json::from_str(&model_raw).map_err(|error| format!("Invalid model {}: {}", path, error))
+    }
+
+    pub fn save(&self, path: &str) {
+        let serialized = serde_json::to_string(self).unwrap();
+
+        let mut f = File::create(path).expect("Unable to create file");
+        f.write_all(serialized.as_bytes()).expect("Unable to write data");
+    }
+}
+
+impl Detector for Model {
+    fn predict_window(&self, image_data: &ImageData, integral_image: &IntegralImage) -> bool {
+        match *self {
+            Model::Cascade(ref ensemble) => ensemble.predict_window(image_data, integral_image),
+            Model::SoftCascade(ref cascade) => cascade.predict_window(image_data, integral_image),
+            Model::Hog(ref hog) => hog.predict_window(image_data, integral_image),
+        }
+    }
+
+    fn score_window(&self, image_data: &ImageData, integral_image: &IntegralImage) -> Option<f64> {
+        match *self {
+            Model::Cascade(ref ensemble) => ensemble.score_window(image_data, integral_image),
+            Model::SoftCascade(ref cascade) => cascade.score_window(image_data, integral_image),
+            Model::Hog(ref hog) => hog.score_window(image_data, integral_image),
+        }
+    }
+
+    fn compile(&self, layout: &WindowLayout) -> Option<CompiledCascade> {
+        match *self {
+            Model::Cascade(ref ensemble) => ensemble.compile(layout),
+            Model::SoftCascade(ref cascade) => cascade.compile(layout),
+            Model::Hog(ref hog) => hog.compile(layout),
+        }
+    }
+}
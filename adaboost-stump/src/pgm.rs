@@ -0,0 +1,133 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use image::{GrayImage, Luma};
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+// splits the header into whitespace separated tokens, skipping `#` comments,
+// and returns the position right after the last one
+fn read_header(bytes: &[u8], num_tokens: usize) -> io::Result<(Vec<String>, usize)> {
+    let mut tokens = Vec::new();
+    let mut position = 0;
+
+    while tokens.len() < num_tokens {
+        if position >= bytes.len() {
+            return Err(invalid_data("Truncated PGM header"));
+        }
+
+        match bytes[position] {
+            b'#' => {
+                while position < bytes.len() && bytes[position] != b'\n' {
+                    position += 1;
+                }
+            }
+            byte if (byte as char).is_whitespace() => position += 1,
+            _ => {
+                let start = position;
+                while position < bytes.len() && !(bytes[position] as char).is_whitespace() {
+                    position += 1;
+                }
+                tokens.push(String::from_utf8_lossy(&bytes[start..position]).into_owned());
+            }
+        }
+    }
+
+    Ok((tokens, position))
+}
+
+fn parse_number(token: &str) -> io::Result<u32> {
+    token.parse().map_err(|_| invalid_data("Invalid number in PGM header"))
+}
+
+// reads binary (P5) and plain (P2) portable graymaps, such as the CBCL faces
+pub fn decode_pgm(bytes: &[u8]) -> io::Result<GrayImage> {
+    let (tokens, position) = read_header(bytes, 4)?;
+
+    let width = parse_number(&tokens[1])?;
+    let height = parse_number(&tokens[2])?;
+    let max_value = parse_number(&tokens[3])?;
+    let num_pixels = width.checked_mul(height)
+        .ok_or_else(|| invalid_data("PGM dimensions too large"))? as usize;
+
+    if max_value == 0 || max_value > 65535 {
+        return Err(invalid_data("Invalid PGM maximum value"));
+    }
+
+    let samples: Vec<u32> = match tokens[0].as_str() {
+        "P5" => {
+            // a single whitespace character separates the header from the raster
+            let raster = &bytes[(position + 1).min(bytes.len())..];
+            let bytes_per_sample = if max_value > 255 { 2 } else { 1 };
+
+            if raster.len() / bytes_per_sample < num_pixels {
+                return Err(invalid_data("Truncated PGM raster"));
+            }
+
+            raster.chunks(bytes_per_sample)
+                .take(num_pixels)
+                .map(|sample| sample.iter().fold(0, |acc, &byte| (acc << 8) | byte as u32))
+                .collect()
+        }
+        "P2" => {
+            let raster = String::from_utf8_lossy(&bytes[position..]).into_owned();
+            let samples: Vec<u32> = raster.split_whitespace()
+                .take(num_pixels)
+                .map(|token| parse_number(token))
+                .collect::<io::Result<_>>()?;
+
+            if samples.len() < num_pixels {
+                return Err(invalid_data("Truncated PGM raster"));
+            }
+
+            samples
+        }
+        _ => return Err(invalid_data("Not a PGM file")),
+    };
+
+    // rescale to 8 bits
+    Ok(GrayImage::from_fn(width, height, |x, y| {
+        let sample = samples[(y * width + x) as usize].min(max_value);
+        Luma([(sample * 255 / max_value) as u8])
+    }))
+}
+
+pub fn read_pgm<P: AsRef<Path>>(path: P) -> io::Result<GrayImage> {
+    let mut bytes = Vec::new();
+    File::open(path)?.read_to_end(&mut bytes)?;
+
+    decode_pgm(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_and_plain_rasters_decode_alike() {
+        let binary = decode_pgm(b"P5\n# a comment\n3 2\n255\n\x00\x10\x20\x30\x40\xff").unwrap();
+        let plain = decode_pgm(b"P2 3 2 255\n0 16 32\n48 64 255\n").unwrap();
+
+        assert_eq!(binary.dimensions(), (3, 2));
+        assert_eq!(binary.into_raw(), vec![0, 16, 32, 48, 64, 255]);
+        assert_eq!(plain.into_raw(), vec![0, 16, 32, 48, 64, 255]);
+    }
+
+    #[test]
+    fn sixteen_bit_samples_are_rescaled() {
+        let image = decode_pgm(b"P5 2 1 65535\n\x00\x00\xff\xff").unwrap();
+
+        assert_eq!(image.into_raw(), vec![0, 255]);
+    }
+
+    #[test]
+    fn broken_files_are_errors() {
+        assert!(decode_pgm(b"P5 3 2 255\n\x00\x10").is_err());
+        assert!(decode_pgm(b"P2 3 2").is_err());
+        assert!(decode_pgm(b"P6 1 1 255\n\x00").is_err());
+        assert!(decode_pgm(b"P5 1 1 0\n\x00").is_err());
+        assert!(decode_pgm(b"P5 65536 65536 255\n\x00").is_err());
+    }
+}
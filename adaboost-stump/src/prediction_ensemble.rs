@@ -1,9 +1,12 @@
 use integral_image::IntegralImage;
-use haar_like_feature::HaarLikeFeature;
+use weak_classifier::WeakClassifier;
+use detector::Detector;
+use compiled_cascade::{CompiledCascade, WindowLayout};
+use shared::ImageData;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct PredictionEnsemble {
-    ensemble: Vec<Vec<HaarLikeFeature>>,
+    ensemble: Vec<Vec<WeakClassifier>>,
 }
 
 impl PredictionEnsemble {
@@ -11,21 +14,49 @@ impl PredictionEnsemble {
         PredictionEnsemble { ensemble: Vec::new() }
     }
 
-    pub fn predict(&self, integral_image: &IntegralImage) -> bool {
+    // vote of the last stage for a window every stage accepts
+    pub fn score(&self, integral_image: &IntegralImage) -> Option<f64> {
+        let mut vote = 0.0;
+
         for composition in self.ensemble.iter() {
-            let prediction = composition.iter()
-                .fold(0.0, |acc, ref h| acc + h.predict(&integral_image))
-                .signum();
+            vote = composition.iter()
+                .fold(0.0, |acc, ref h| acc + h.predict(&integral_image));
 
-            if prediction < 0.0 {
-                return false;
+            if vote.signum() < 0.0 {
+                return None;
             }
         }
 
-        return true;
+        Some(vote)
+    }
+
+    pub fn predict(&self, integral_image: &IntegralImage) -> bool {
+        self.score(integral_image).is_some()
+    }
+
+    pub fn stages(&self) -> &Vec<Vec<WeakClassifier>> {
+        &self.ensemble
+    }
+
+    pub fn into_stages(self) -> Vec<Vec<WeakClassifier>> {
+        self.ensemble
     }
 
-    pub fn push(&mut self, prediction: Vec<HaarLikeFeature>) {
+    pub fn push(&mut self, prediction: Vec<WeakClassifier>) {
         self.ensemble.push(prediction);
     }
 }
+
+impl Detector for PredictionEnsemble {
+    fn predict_window(&self, _: &ImageData, integral_image: &IntegralImage) -> bool {
+        self.predict(integral_image)
+    }
+
+    fn score_window(&self, _: &ImageData, integral_image: &IntegralImage) -> Option<f64> {
+        self.score(integral_image)
+    }
+
+    fn compile(&self, layout: &WindowLayout) -> Option<CompiledCascade> {
+        Some(CompiledCascade::from_stages(&self.ensemble, layout))
+    }
+}
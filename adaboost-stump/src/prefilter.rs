@@ -0,0 +1,277 @@
+use image::{GrayImage, Luma, Pixel, RgbImage};
+use image::imageops::flip_horizontal;
+use fast_integral_image::FastIntegralImage;
+use geometry::Rect;
+use args;
+
+// chroma ranges of skin in YCbCr whatever the brightness (Chai and Ngan, 1999)
+const SKIN_CB: (f64, f64) = (77.0, 127.0);
+const SKIN_CR: (f64, f64) = (133.0, 173.0);
+
+// candidate pixels grow by this much, to cover the eyes, the mouth and the
+// edges the frame differences leave around a moving face
+const SKIN_RADIUS: usize = 2;
+const MOTION_RADIUS: usize = 4;
+
+// builds a mask of the pixels where a face may be before the cascade runs;
+// 255 marks a candidate
+#[derive(Debug)]
+pub enum Prefilter {
+    // pixels whose colour is in the skin range
+    Skin,
+    // pixels that changed by more than `threshold` since the previous frame
+    Motion {
+        threshold: u8,
+        previous: Option<GrayImage>,
+    },
+}
+
+// pixels within `radius` of a candidate become candidates
+fn dilate(mask: &GrayImage, radius: usize) -> GrayImage {
+    let (width, height) = (mask.width() as usize, mask.height() as usize);
+    let integral_image = FastIntegralImage::build(mask);
+
+    let pixels = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let (left, top) = (x.saturating_sub(radius), y.saturating_sub(radius));
+            let (right, bottom) = ((x + radius + 1).min(width), (y + radius + 1).min(height));
+            let area = Rect::new(left, top, right - left, bottom - top);
+
+            if integral_image.sum_region(&area) > 0 { 255 } else { 0 }
+        })
+        .collect();
+
+    GrayImage::from_raw(width as u32, height as u32, pixels).unwrap()
+}
+
+impl Prefilter {
+    pub fn motion(threshold: u8) -> Prefilter {
+        Prefilter::Motion {
+            threshold: threshold,
+            previous: None,
+        }
+    }
+
+    // None keeps every pixel: the skin filter without colours, or the motion
+    // filter on the first frame or after a change of size
+    pub fn mask(&mut self, gray: &GrayImage, colour: Option<&RgbImage>) -> Option<GrayImage> {
+        match *self {
+            Prefilter::Skin => {
+                let colour = colour?;
+
+                let mask = GrayImage::from_fn(colour.width(), colour.height(), |x, y| {
+                    let rgb = colour.get_pixel(x, y).channels();
+                    let (r, g, b) = (rgb[0] as f64, rgb[1] as f64, rgb[2] as f64);
+
+                    let cb = 128.0 - 0.168736 * r - 0.331264 * g + 0.5 * b;
+                    let cr = 128.0 + 0.5 * r - 0.418688 * g - 0.081312 * b;
+
+                    let is_skin = cb >= SKIN_CB.0 && cb <= SKIN_CB.1 && cr >= SKIN_CR.0 &&
+                                  cr <= SKIN_CR.1;
+                    Luma([if is_skin { 255 } else { 0 }])
+                });
+
+                Some(dilate(&mask, SKIN_RADIUS))
+            }
+            Prefilter::Motion { threshold, ref mut previous } => {
+                let mask = match *previous {
+                    Some(ref previous) if previous.dimensions() == gray.dimensions() => {
+                        let pixels = gray.iter()
+                            .zip(previous.iter())
+                            .map(|(&a, &b)| {
+                                if (a as i16 - b as i16).abs() > threshold as i16 { 255 } else { 0 }
+                            })
+                            .collect();
+
+                        let mask = GrayImage::from_raw(gray.width(), gray.height(), pixels);
+                        Some(dilate(&mask.unwrap(), MOTION_RADIUS))
+                    }
+                    _ => None,
+                };
+
+                *previous = Some(gray.clone());
+                mask
+            }
+        }
+    }
+}
+
+// the prefilters of a run; a pixel stays a candidate if all of them keep it
+#[derive(Debug)]
+pub struct Prefilters {
+    filters: Vec<Prefilter>,
+}
+
+impl Prefilters {
+    pub fn new(filters: Vec<Prefilter>) -> Prefilters {
+        Prefilters { filters: filters }
+    }
+
+    // --skin, and --motion with --motion-threshold (15 by default)
+    pub fn from_args() -> Prefilters {
+        let mut filters = Vec::new();
+
+        if args::get_flag("skin") {
+            filters.push(Prefilter::Skin);
+        }
+
+        if args::get_flag("motion") {
+            filters.push(Prefilter::motion(args::get_option("motion-threshold").unwrap_or(15)));
+        }
+
+        Prefilters::new(filters)
+    }
+
+    // every filter sees every frame, so that the motion filter always compares
+    // with the frame just before
+    pub fn mask(&mut self, gray: &GrayImage, colour: Option<&RgbImage>) -> Option<GrayImage> {
+        self.filters
+            .iter_mut()
+            .map(|filter| filter.mask(gray, colour))
+            .fold(None, |acc, mask| match (acc, mask) {
+                (Some(mut acc), Some(mask)) => {
+                    for (a, &b) in acc.iter_mut().zip(mask.iter()) {
+                        *a = (*a).min(b);
+                    }
+                    Some(acc)
+                }
+                (acc, mask) => acc.or(mask),
+            })
+    }
+}
+
+// candidate pixels counted under a window with a summed table; windows with
+// less than `min_coverage` of their pixels marked are not evaluated
+#[derive(Debug)]
+pub struct CandidateMask {
+    mask: GrayImage,
+    integral_image: FastIntegralImage,
+    min_coverage: f64,
+}
+
+impl CandidateMask {
+    pub fn new(mask: &GrayImage, min_coverage: f64) -> CandidateMask {
+        CandidateMask {
+            mask: mask.clone(),
+            integral_image: FastIntegralImage::build(mask),
+            min_coverage: min_coverage,
+        }
+    }
+
+    // the mask of the mirror image
+    pub fn mirrored(&self) -> CandidateMask {
+        CandidateMask::new(&flip_horizontal(&self.mask), self.min_coverage)
+    }
+
+    pub fn accepts(&self, window: &Rect) -> bool {
+        let marked = self.integral_image.sum_region(window) as f64 / 255.0;
+        marked >= self.min_coverage * window.area() as f64
+    }
+}
+
+// share of the picture the mask keeps
+pub fn coverage(mask: &GrayImage) -> f64 {
+    let marked = mask.iter().fold(0.0, |acc, &value| acc + value as f64 / 255.0);
+    marked / (mask.width() * mask.height()).max(1) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    const SKIN: [u8; 3] = [224, 172, 145];
+    const SKY: [u8; 3] = [40, 60, 200];
+
+    fn marked(mask: &GrayImage) -> Vec<(u32, u32)> {
+        mask.enumerate_pixels()
+            .filter(|&(_, _, pixel)| pixel[0] == 255)
+            .map(|(x, y, _)| (x, y))
+            .collect()
+    }
+
+    // 255 on the columns before `edge`
+    fn left_of(width: u32, height: u32, edge: u32) -> GrayImage {
+        GrayImage::from_fn(width, height, |x, _| Luma([if x < edge { 255 } else { 0 }]))
+    }
+
+    #[test]
+    fn dilation_grows_candidates_by_the_radius() {
+        let mut mask = GrayImage::new(12, 10);
+        mask.put_pixel(5, 5, Luma([255]));
+        mask.put_pixel(0, 0, Luma([255]));
+
+        let dilated = dilate(&mask, 2);
+        for (x, y, pixel) in dilated.enumerate_pixels() {
+            let near_middle = (x as i32 - 5).abs() <= 2 && (y as i32 - 5).abs() <= 2;
+            let near_corner = x <= 2 && y <= 2;
+
+            assert_eq!(pixel[0] == 255, near_middle || near_corner, "({}, {})", x, y);
+        }
+
+        assert_eq!(marked(&dilate(&mask, 0)), vec![(0, 0), (5, 5)]);
+        assert!(marked(&dilate(&GrayImage::new(12, 10), 3)).is_empty());
+    }
+
+    #[test]
+    fn skin_is_told_from_other_colours() {
+        let colour = RgbImage::from_fn(20, 10, |x, _| Rgb(if x < 10 { SKIN } else { SKY }));
+        let gray = GrayImage::new(20, 10);
+
+        let mask = Prefilter::Skin.mask(&gray, Some(&colour)).unwrap();
+        assert_eq!(marked(&mask), marked(&left_of(20, 10, 10 + SKIN_RADIUS as u32)));
+
+        let grey = RgbImage::from_pixel(20, 10, Rgb([128, 128, 128]));
+        assert!(marked(&Prefilter::Skin.mask(&gray, Some(&grey)).unwrap()).is_empty());
+
+        // gray pictures keep every pixel
+        assert!(Prefilter::Skin.mask(&gray, None).is_none());
+    }
+
+    #[test]
+    fn prefilters_keep_the_pixels_all_of_them_keep() {
+        let colour = RgbImage::from_fn(20, 10, |x, _| Rgb(if x < 10 { SKIN } else { SKY }));
+        let skin_mask = left_of(20, 10, 10 + SKIN_RADIUS as u32);
+
+        assert!(Prefilters::new(Vec::new())
+            .mask(&GrayImage::new(20, 10), Some(&colour))
+            .is_none());
+
+        let mut prefilters = Prefilters::new(vec![Prefilter::Skin, Prefilter::motion(15)]);
+
+        // no previous frame yet, so the motion filter keeps every pixel
+        let still = GrayImage::new(20, 10);
+        let mask = prefilters.mask(&still, Some(&colour)).unwrap();
+        assert_eq!(marked(&mask), marked(&skin_mask));
+
+        // something moves in the columns 4 to 7, 18 and 19
+        let moved = GrayImage::from_fn(20, 10, |x, _| {
+            Luma([if (x >= 4 && x < 8) || x >= 18 { 200 } else { 0 }])
+        });
+        let mask = prefilters.mask(&moved, Some(&colour)).unwrap();
+        assert_eq!(marked(&mask), marked(&left_of(20, 10, 12)));
+
+        // without colours the skin filter keeps every pixel and motion decides
+        let mask = prefilters.mask(&still, None).unwrap();
+        let motion = GrayImage::from_fn(20, 10, |x, _| {
+            Luma([if x < 12 || x >= 18 - MOTION_RADIUS as u32 { 255 } else { 0 }])
+        });
+        assert_eq!(marked(&mask), marked(&motion));
+    }
+
+    #[test]
+    fn windows_need_enough_candidate_pixels() {
+        let candidates = CandidateMask::new(&left_of(40, 20, 20), 0.4);
+
+        assert!(candidates.accepts(&Rect::new(0, 0, 19, 19)));
+        assert!(!candidates.accepts(&Rect::new(21, 0, 19, 19)));
+        // half of the window is marked
+        assert!(candidates.accepts(&Rect::new(10, 0, 20, 19)));
+        assert!(!CandidateMask::new(&left_of(40, 20, 20), 0.6).accepts(&Rect::new(10, 0, 20, 19)));
+
+        let mirrored = candidates.mirrored();
+        assert!(!mirrored.accepts(&Rect::new(0, 0, 19, 19)));
+        assert!(mirrored.accepts(&Rect::new(21, 0, 19, 19)));
+    }
+}
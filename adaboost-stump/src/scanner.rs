@@ -0,0 +1,535 @@
+use std::collections::VecDeque;
+use image::{imageops, FilterType, GrayImage, Pixel};
+use rulinalg::matrix::Matrix;
+use integral_image::IntegralImage;
+use fast_integral_image::FastIntegralImage;
+use compiled_cascade::{CompiledCascade, WindowLayout};
+use detection_params::{DetectionParams, ScanLevel};
+use detector::Detector;
+use prefilter::CandidateMask;
+use geometry::{Point, Rect, RotatedRect, Size};
+use warp::Rotation;
+use annotations::FaceFeature;
+use shared::{ImageData, WINDOW_HEIGHT, WINDOW_WIDTH};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Detection {
+    pub rect: Rect,
+    // filled in by a landmark regressor after the scan
+    pub landmarks: Option<FaceFeature>,
+    // cascade of a bundle that found it
+    pub label: Option<String>,
+    // windows grouped into this detection, 1 for a single window
+    pub neighbours: usize,
+    // margin of the window over the last threshold of the detector, the
+    // highest one of the windows when grouped
+    pub score: f64,
+    // the box as found on a turned copy of the picture; `rect` bounds it
+    pub rotation: Option<RotatedRect>,
+}
+
+impl Detection {
+    pub fn new(rect: Rect, score: f64) -> Detection {
+        Detection {
+            rect: rect,
+            landmarks: None,
+            label: None,
+            neighbours: 1,
+            score: score,
+            rotation: None,
+        }
+    }
+
+    // the box, turned or not
+    pub fn rotated_rect(&self) -> RotatedRect {
+        self.rotation.unwrap_or_else(|| RotatedRect::from_rect(&self.rect))
+    }
+
+    // intersection over union, of the turned boxes when either is turned
+    pub fn overlap(&self, other: &Detection) -> f64 {
+        if self.rotation.is_none() && other.rotation.is_none() {
+            self.rect.overlap(&other.rect)
+        } else {
+            self.rotated_rect().overlap(&other.rotated_rect())
+        }
+    }
+}
+
+// luminosity of the window normalized by its brightest pixel
+fn get_window(data: &GrayImage, window: &Rect) -> ImageData {
+    let mut mat = Matrix::zeros(window.height, window.width);
+    let mut max = 1.0;
+
+    for y in 0..window.height {
+        for x in 0..window.width {
+            let pixel = data.get_pixel((window.x + x) as u32, (window.y + y) as u32);
+            let value = (pixel.channels()[0]) as f64;
+            mat[[y, x]] = value;
+
+            if value > max {
+                max = value;
+            }
+        }
+    }
+
+    mat / max
+}
+
+// largest of every `window` consecutive values. the deque holds the indices of
+// the values no later one has beaten yet, largest first, so each value goes in
+// and out once whatever the window
+fn sliding_maxima(values: &[u8], window: usize) -> Vec<u8> {
+    let mut candidates: VecDeque<usize> = VecDeque::with_capacity(window);
+    let mut maxima = Vec::with_capacity(values.len() + 1 - window);
+
+    for (i, &value) in values.iter().enumerate() {
+        while candidates.back().map_or(false, |&j| values[j] <= value) {
+            candidates.pop_back();
+        }
+        candidates.push_back(i);
+
+        if *candidates.front().unwrap() + window <= i {
+            candidates.pop_front();
+        }
+
+        if i + 1 >= window {
+            maxima.push(values[*candidates.front().unwrap()]);
+        }
+    }
+
+    maxima
+}
+
+// brightest pixel of every window of the given size, row after row of window
+// positions, found with a sliding maximum along the rows and another along the
+// columns of the row maxima
+pub fn window_maxima(data: &GrayImage, window_size: Size) -> Vec<u8> {
+    let (width, height) = (data.width() as usize, data.height() as usize);
+    let (positions_x, positions_y) = (width - window_size.width + 1,
+                                      height - window_size.height + 1);
+    let pixels: &[u8] = &**data;
+
+    let mut row_maxima = Vec::with_capacity(positions_x * height);
+    for y in 0..height {
+        row_maxima.extend(sliding_maxima(&pixels[y * width..(y + 1) * width], window_size.width));
+    }
+
+    let mut maxima = vec![0; positions_x * positions_y];
+    for x in 0..positions_x {
+        let column: Vec<u8> = (0..height).map(|y| row_maxima[y * positions_x + x]).collect();
+
+        for (y, max) in sliding_maxima(&column, window_size.height).into_iter().enumerate() {
+            maxima[y * positions_x + x] = max;
+        }
+    }
+
+    maxima
+}
+
+// window positions along a side of `length` pixels
+pub fn positions(length: usize, window: usize, step: usize) -> Vec<usize> {
+    if length < window {
+        return Vec::new();
+    }
+
+    (0..(length - window) / step + 1).map(|i| i * step).collect()
+}
+
+// copy of the part of the picture under `rect`
+pub fn crop(data: &GrayImage, rect: &Rect) -> GrayImage {
+    let width = data.width() as usize;
+    let pixels: &[u8] = &**data;
+
+    let mut cropped = Vec::with_capacity(rect.area());
+    for y in rect.y..rect.bottom() {
+        cropped.extend_from_slice(&pixels[y * width + rect.x..y * width + rect.right()]);
+    }
+
+    GrayImage::from_raw(rect.width as u32, rect.height as u32, cropped).unwrap()
+}
+
+// runs `scan_image` over the region of interest of `params` with its window
+// sizes, then over copies of the region turned by each of its angles, and
+// brings the `num_lists` lists of detections back to the picture. detections
+// of a turned copy are turned boxes whose centre falls inside the region.
+// `candidates` is the mask of the prefilters, cut and turned with the picture
+pub fn scan_rotations<F>(data: &GrayImage,
+                         candidates: Option<&GrayImage>,
+                         params: &DetectionParams,
+                         num_lists: usize,
+                         mut scan_image: F)
+                         -> Vec<Vec<Detection>>
+    where F: FnMut(&GrayImage, Option<&CandidateMask>, &Vec<ScanLevel>) -> Vec<Vec<Detection>>
+{
+    let mut colls = vec![Vec::new(); num_lists];
+
+    let region = match params.region(data.width() as usize, data.height() as usize) {
+        Some(region) => region,
+        None => return colls,
+    };
+
+    let cropped = crop(data, &region);
+    let cropped_candidates = candidates.map(|candidates| crop(candidates, &region));
+
+    let mask = cropped_candidates.as_ref()
+        .map(|candidates| CandidateMask::new(candidates, params.min_coverage));
+    let upright = scan_image(&cropped, mask.as_ref(), &params.levels(region.size()));
+    for (coll, detections) in colls.iter_mut().zip(upright.into_iter()) {
+        coll.extend(detections.into_iter().map(|mut detection| {
+            detection.rect = detection.rect.offset(region.origin());
+            detection
+        }));
+    }
+
+    for &angle in params.angles.iter() {
+        let rotation = Rotation::new(cropped.dimensions(), angle.to_radians());
+        let turned = rotation.apply(&cropped);
+        let canvas_size = Size::new(turned.width() as usize, turned.height() as usize);
+
+        let mask = cropped_candidates.as_ref().map(|candidates| {
+            CandidateMask::new(&rotation.apply(candidates), params.min_coverage)
+        });
+        let found = scan_image(&turned, mask.as_ref(), &params.levels(canvas_size));
+
+        for (coll, detections) in colls.iter_mut().zip(found.into_iter()) {
+            coll.extend(detections.into_iter().filter_map(|mut detection| {
+                let upright = RotatedRect::from_rect(&detection.rect);
+                let (cx, cy) = rotation.to_original((upright.cx, upright.cy));
+
+                if cx < 0.0 || cy < 0.0 || cx >= region.width as f64 ||
+                   cy >= region.height as f64 {
+                    return None;
+                }
+
+                let turned_rect = RotatedRect {
+                    cx: cx + region.x as f64,
+                    cy: cy + region.y as f64,
+                    angle: angle,
+                    ..upright
+                };
+
+                let bounds = turned_rect.bounding_rect();
+                let (right, bottom) = (bounds.right().min(data.width() as usize),
+                                       bounds.bottom().min(data.height() as usize));
+
+                detection.rect = Rect::new(bounds.x, bounds.y, right - bounds.x, bottom - bounds.y);
+                detection.rotation = Some(turned_rect);
+                Some(detection)
+            }));
+        }
+    }
+
+    colls
+}
+
+// slides windows of every size of `params` over its region of interest, and
+// over turned copies of it, and keeps the accepted ones, on the integer
+// integral image when the detector can be compiled
+pub fn scan<D: Detector>(detector: &D,
+                         data: &GrayImage,
+                         candidates: Option<&GrayImage>,
+                         params: &DetectionParams)
+                         -> Vec<Detection> {
+    scan_rotations(data, candidates, params, 1, |image, mask, levels| {
+            let mut coll = Vec::new();
+
+            for level in levels.iter() {
+                let layout = WindowLayout::new(image.width() as usize + 1, level.scale);
+
+                coll.extend(match detector.compile(&layout) {
+                    Some(cascade) => scan_compiled(&cascade, image, level, mask),
+                    None => scan_windows(detector, image, level, mask),
+                });
+            }
+
+            vec![coll]
+        })
+        .pop()
+        .unwrap()
+}
+
+// builds the normalized matrix and integral image of every window. windows
+// larger than 19x19 are taken from a copy of the picture shrunk to match.
+// windows the mask does not accept are skipped
+pub fn scan_windows<D: Detector>(detector: &D,
+                                 data: &GrayImage,
+                                 level: &ScanLevel,
+                                 mask: Option<&CandidateMask>)
+                                 -> Vec<Detection> {
+    let (width, height) = (data.width() as usize, data.height() as usize);
+    let window_size = Size::new(WINDOW_WIDTH, WINDOW_HEIGHT);
+
+    let resized;
+    let scaled = if level.window == window_size {
+        data
+    } else {
+        resized = imageops::resize(data,
+                                   (width as f64 / level.scale).round().max(1.0) as u32,
+                                   (height as f64 / level.scale).round().max(1.0) as u32,
+                                   FilterType::Triangle);
+        &resized
+    };
+
+    let step_x = ((level.step_x as f64 / level.scale).round() as usize).max(1);
+    let step_y = ((level.step_y as f64 / level.scale).round() as usize).max(1);
+
+    let mut coll = Vec::new();
+
+    for y in positions(scaled.height() as usize, window_size.height, step_y) {
+        for x in positions(scaled.width() as usize, window_size.width, step_x) {
+            // in pixels of the picture, kept inside it
+            let original_x = ((x as f64 * level.scale).round() as usize)
+                .min(width - level.window.width);
+            let original_y = ((y as f64 * level.scale).round() as usize)
+                .min(height - level.window.height);
+            let original = Rect::from_parts(Point::new(original_x, original_y), level.window);
+
+            if mask.map_or(false, |mask| !mask.accepts(&original)) {
+                continue;
+            }
+
+            let window = Rect::from_parts(Point::new(x, y), window_size);
+
+            let mat = get_window(scaled, &window);
+            let integral_image = IntegralImage::build(&mat);
+
+            if let Some(score) = detector.score_window(&mat, &integral_image) {
+                coll.push(Detection::new(original, score));
+            }
+        }
+    }
+
+    coll
+}
+
+// one integral image for the whole picture; the cascade has to be compiled
+// for a stride of the image width plus one and the scale of the level
+pub fn scan_compiled(cascade: &CompiledCascade,
+                     data: &GrayImage,
+                     level: &ScanLevel,
+                     mask: Option<&CandidateMask>)
+                     -> Vec<Detection> {
+    scan_compiled_all(&vec![cascade], data, level, mask).pop().unwrap()
+}
+
+// every cascade looks at each window while its pixels are still in the cache;
+// the detections of each cascade come out in a list of their own
+pub fn scan_compiled_all(cascades: &Vec<&CompiledCascade>,
+                         data: &GrayImage,
+                         level: &ScanLevel,
+                         mask: Option<&CandidateMask>)
+                         -> Vec<Vec<Detection>> {
+    let (width, height) = (data.width() as usize, data.height() as usize);
+    let window_size = level.window;
+
+    let mut colls = vec![Vec::new(); cascades.len()];
+
+    if width < window_size.width || height < window_size.height {
+        return colls;
+    }
+
+    let integral_image = FastIntegralImage::build(data);
+    let maxima = window_maxima(data, window_size);
+    let positions_x = width - window_size.width + 1;
+
+    for y in positions(height, window_size.height, level.step_y) {
+        for x in positions(width, window_size.width, level.step_x) {
+            let window = Rect::from_parts(Point::new(x, y), window_size);
+
+            if mask.map_or(false, |mask| !mask.accepts(&window)) {
+                continue;
+            }
+
+            // same floor of 1 as get_window
+            let max = (maxima[y * positions_x + x] as f64).max(1.0);
+            let origin = integral_image.origin(x, y);
+
+            for (cascade, coll) in cascades.iter().zip(colls.iter_mut()) {
+                if let Some(score) = cascade.score(&integral_image, origin, max) {
+                    coll.push(Detection::new(window, score));
+                }
+            }
+        }
+    }
+
+    colls
+}
+
+fn find_cluster(cluster_of: &mut Vec<usize>, i: usize) -> usize {
+    let mut root = i;
+    while cluster_of[root] != root {
+        root = cluster_of[root];
+    }
+    cluster_of[i] = root;
+
+    root
+}
+
+// detections chained together by overlaps of at least `min_overlap`
+fn cluster_detections(detections: &Vec<Detection>, min_overlap: f64) -> Vec<Vec<&Detection>> {
+    let mut cluster_of: Vec<usize> = (0..detections.len()).collect();
+
+    for i in 0..detections.len() {
+        for j in (i + 1)..detections.len() {
+            if detections[i].overlap(&detections[j]) >= min_overlap {
+                let (a, b) = (find_cluster(&mut cluster_of, i), find_cluster(&mut cluster_of, j));
+                cluster_of[a] = b;
+            }
+        }
+    }
+
+    let mut clusters: Vec<Vec<&Detection>> = vec![Vec::new(); detections.len()];
+    for (i, detection) in detections.iter().enumerate() {
+        let root = find_cluster(&mut cluster_of, i);
+        clusters[root].push(detection);
+    }
+
+    clusters.into_iter().filter(|cluster| !cluster.is_empty()).collect()
+}
+
+// clusters detections that overlap by at least `min_overlap` and replaces each
+// cluster with its mean box, dropping clusters with fewer than `min_neighbours`
+// windows. the label is taken from the first detection of the cluster. when
+// any of the boxes is turned the mean is a turned box too
+pub fn group_detections(detections: &Vec<Detection>,
+                        min_overlap: f64,
+                        min_neighbours: usize)
+                        -> Vec<Detection> {
+    cluster_detections(detections, min_overlap)
+        .into_iter()
+        .map(|cluster| {
+            let neighbours = cluster.iter().fold(0, |acc, detection| acc + detection.neighbours);
+            (cluster, neighbours)
+        })
+        .filter(|&(_, neighbours)| neighbours >= min_neighbours)
+        .map(|(cluster, neighbours)| {
+            let n = cluster.len();
+            let (rect, rotation) = if cluster.iter().all(|detection| detection.rotation.is_none()) {
+                let sum = cluster.iter().fold((0, 0, 0, 0), |acc, detection| {
+                    let rect = detection.rect;
+                    (acc.0 + rect.x, acc.1 + rect.y, acc.2 + rect.width, acc.3 + rect.height)
+                });
+
+                (Rect::new(sum.0 / n, sum.1 / n, sum.2 / n, sum.3 / n), None)
+            } else {
+                let sum = cluster.iter().fold((0.0, 0.0, 0.0, 0.0, 0.0), |acc, detection| {
+                    let turned = detection.rotated_rect();
+                    (acc.0 + turned.cx,
+                     acc.1 + turned.cy,
+                     acc.2 + turned.width,
+                     acc.3 + turned.height,
+                     acc.4 + turned.angle)
+                });
+
+                let n = n as f64;
+                let mean = RotatedRect {
+                    cx: sum.0 / n,
+                    cy: sum.1 / n,
+                    width: sum.2 / n,
+                    height: sum.3 / n,
+                    angle: sum.4 / n,
+                };
+
+                (mean.bounding_rect(), Some(mean))
+            };
+
+            Detection {
+                rect: rect,
+                landmarks: None,
+                label: cluster[0].label.clone(),
+                neighbours: neighbours,
+                score: cluster.iter()
+                    .fold(::std::f64::NEG_INFINITY, |acc, detection| acc.max(detection.score)),
+                rotation: rotation,
+            }
+        })
+        .collect()
+}
+
+// merges grouped detections of different cascades that overlap, keeping the
+// one most windows agreed on
+pub fn merge_labels(detections: &Vec<Detection>, min_overlap: f64) -> Vec<Detection> {
+    cluster_detections(detections, min_overlap)
+        .into_iter()
+        .map(|cluster| {
+            let best = cluster.iter()
+                .fold(cluster[0], |best, &detection| {
+                    if detection.neighbours > best.neighbours { detection } else { best }
+                });
+
+            best.clone()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng, StdRng};
+
+    #[test]
+    fn sliding_maxima_follow_the_window() {
+        let values = [3, 1, 4, 1, 5, 9, 2, 6, 5, 3];
+
+        assert_eq!(sliding_maxima(&values, 1), values.to_vec());
+        assert_eq!(sliding_maxima(&values, 3), vec![4, 4, 5, 9, 9, 9, 6, 6]);
+        assert_eq!(sliding_maxima(&values, 10), vec![9]);
+        assert_eq!(sliding_maxima(&[7, 7, 7, 2], 2), vec![7, 7, 7]);
+    }
+
+    #[test]
+    fn window_maxima_match_brute_force() {
+        let mut rng: StdRng = SeedableRng::from_seed(&[38][..]);
+        let (width, height) = (23, 17);
+        let pixels: Vec<u8> = (0..width * height).map(|_| rng.gen()).collect();
+        let image = GrayImage::from_raw(width as u32, height as u32, pixels.clone()).unwrap();
+
+        for &(window_width, window_height) in [(1, 1), (4, 3), (19, 17), (23, 5)].iter() {
+            let maxima = window_maxima(&image, Size::new(window_width, window_height));
+            let positions_x = width - window_width + 1;
+            assert_eq!(maxima.len(), positions_x * (height - window_height + 1));
+
+            for (i, &max) in maxima.iter().enumerate() {
+                let (x, y) = (i % positions_x, i / positions_x);
+                let expected = (y..y + window_height)
+                    .flat_map(|row| pixels[row * width + x..][..window_width].to_vec())
+                    .max()
+                    .unwrap();
+
+                assert_eq!(max,
+                           expected,
+                           "window {}x{} at {}, {}",
+                           window_width,
+                           window_height,
+                           x,
+                           y);
+            }
+        }
+    }
+
+    #[test]
+    fn chains_of_overlapping_windows_form_one_group() {
+        // a overlaps b and b overlaps c, but a and c overlap too little
+        let detections = vec![Detection::new(Rect::new(0, 0, 20, 20), 0.5),
+                              Detection::new(Rect::new(100, 100, 20, 20), 3.0),
+                              Detection::new(Rect::new(4, 0, 20, 20), 2.0),
+                              Detection::new(Rect::new(8, 0, 20, 20), 1.0)];
+        assert!(detections[0].overlap(&detections[3]) < 0.5);
+
+        let groups = group_detections(&detections, 0.5, 2);
+
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].rect, Rect::new(4, 0, 20, 20));
+        assert_eq!(groups[0].neighbours, 3);
+        assert_eq!(groups[0].score, 2.0);
+    }
+
+    #[test]
+    fn lone_windows_need_enough_neighbours() {
+        let detections = vec![Detection::new(Rect::new(0, 0, 20, 20), 1.0),
+                              Detection::new(Rect::new(50, 0, 20, 20), 1.0)];
+
+        assert_eq!(group_detections(&detections, 0.3, 1).len(), 2);
+        assert!(group_detections(&detections, 0.3, 2).is_empty());
+    }
+}
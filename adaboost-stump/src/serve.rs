@@ -0,0 +1,470 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_json;
+extern crate image;
+extern crate rulinalg;
+extern crate rand;
+
+mod load;
+mod pgm;
+mod manifest;
+mod synthetic;
+mod shared;
+mod geometry;
+mod integral_image;
+mod haar_like_feature;
+mod decision_tree;
+mod lbp_feature;
+mod weak_classifier;
+mod prediction_ensemble;
+mod soft_cascade;
+mod detector;
+mod fast_integral_image;
+mod compiled_cascade;
+mod linear_svm;
+mod hog;
+mod model;
+mod detection_params;
+mod warp;
+mod prefilter;
+mod scanner;
+mod annotations;
+mod http;
+mod args;
+
+use std::fs;
+use std::net::{TcpListener, TcpStream};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use image::DynamicImage;
+use load::to_gray_image;
+use model::Model;
+use detection_params::DetectionParams;
+use prefilter::Prefilter;
+use scanner::{Detection, group_detections, scan};
+use http::{Connection, Request, Response};
+
+// a client silent for longer than this is dropped
+const TIMEOUT_SECONDS: u64 = 30;
+// connections handled at once, those above are turned away with a 503
+const MAX_CONNECTIONS: usize = 64;
+
+struct LoadedModel {
+    model: Model,
+    // modification time of the file it was read from
+    modified: SystemTime,
+    reloads: usize,
+}
+
+// the model currently served, swapped for a new one when its file changes.
+// requests keep the model they started with until they finish
+struct ModelState {
+    path: String,
+    loaded: RwLock<Arc<LoadedModel>>,
+}
+
+#[derive(Serialize, Debug)]
+struct ModelInfo<'a> {
+    path: &'a str,
+    kind: &'static str,
+    // weak classifiers in each stage, a single stage for soft cascades
+    stages: Vec<usize>,
+    modified: u64,
+    reloads: usize,
+}
+
+#[derive(Serialize, Debug)]
+struct DetectResponse {
+    width: u32,
+    height: u32,
+    detections: Vec<Detection>,
+}
+
+fn read_model(path: &str) -> Result<(Model, SystemTime), String> {
+    let modified = fs::metadata(path).and_then(|metadata| metadata.modified())
+        .map_err(|error| error.to_string())?;
+
+    Ok((Model::try_load(path)?, modified))
+}
+
+impl ModelState {
+    fn load(path: &str) -> ModelState {
+        let (model, modified) = read_model(path).expect("Unable to load model");
+
+        ModelState {
+            path: path.to_string(),
+            loaded: RwLock::new(Arc::new(LoadedModel {
+                model: model,
+                modified: modified,
+                reloads: 0,
+            })),
+        }
+    }
+
+    // reloads the file if it changed since it was read; a file that does not
+    // parse (e.g. still being written) leaves the old model in place
+    fn current(&self) -> Arc<LoadedModel> {
+        let loaded = self.loaded.read().unwrap().clone();
+        let modified = fs::metadata(&self.path).and_then(|metadata| metadata.modified());
+
+        match modified {
+            Ok(modified) if modified != loaded.modified => {
+                match read_model(&self.path) {
+                    Ok((model, modified)) => {
+                        println!("Reloaded model {}", self.path);
+
+                        let reloaded = Arc::new(LoadedModel {
+                            model: model,
+                            modified: modified,
+                            reloads: loaded.reloads + 1,
+                        });
+                        *self.loaded.write().unwrap() = reloaded.clone();
+
+                        reloaded
+                    }
+                    Err(error) => {
+                        println!("Keeping the previous model. {}", error);
+                        loaded
+                    }
+                }
+            }
+            _ => loaded,
+        }
+    }
+}
+
+fn seconds_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or(0)
+}
+
+fn model_info(state: &ModelState) -> Response {
+    let loaded = state.current();
+
+    let (kind, stages) = match loaded.model {
+        Model::Cascade(ref ensemble) => {
+            ("cascade", ensemble.stages().iter().map(|stage| stage.len()).collect())
+        }
+        Model::SoftCascade(ref cascade) => ("soft-cascade", vec![cascade.classifiers().len()]),
+        Model::Hog(_) => ("hog", Vec::new()),
+    };
+
+    let info = ModelInfo {
+        path: &state.path,
+        kind: kind,
+        stages: stages,
+        modified: seconds_since_epoch(loaded.modified),
+        reloads: loaded.reloads,
+    };
+
+    Response::ok(serde_json::to_string(&info).unwrap())
+}
+
+// the value of a query parameter, None without one
+fn query_option<T: FromStr>(request: &Request, name: &str) -> Result<Option<T>, Response> {
+    match request.query_param(name) {
+        Some(value) => {
+            value.parse()
+                .map(Some)
+                .map_err(|_| Response::error(400, &format!("Invalid {}", name)))
+        }
+        None => Ok(None),
+    }
+}
+
+// how a request is answered: the options of the server, with those of the
+// query on top
+#[derive(Debug, Clone)]
+struct DetectOptions {
+    params: DetectionParams,
+    min_neighbours: usize,
+    // only windows on skin coloured pixels are evaluated
+    skin: bool,
+}
+
+impl DetectOptions {
+    fn from_args() -> DetectOptions {
+        DetectOptions {
+            params: DetectionParams::from_args(),
+            min_neighbours: args::get_option("min-neighbours").unwrap_or(3),
+            skin: args::get_flag("skin"),
+        }
+    }
+
+    fn with_query(&self, request: &Request) -> Result<DetectOptions, Response> {
+        let defaults = &self.params;
+
+        let params = DetectionParams {
+            min_size: query_option(request, "min_size")?.unwrap_or(defaults.min_size),
+            max_size: query_option(request, "max_size")?.or(defaults.max_size),
+            max_detections: query_option(request, "max_detections")?.or(defaults.max_detections),
+            ..defaults.clone()
+        };
+        params.check_sizes().map_err(|message| Response::error(400, &message))?;
+
+        Ok(DetectOptions {
+            params: params,
+            min_neighbours: query_option(request, "min_neighbours")?.unwrap_or(self.min_neighbours),
+            skin: query_option(request, "skin")?.unwrap_or(self.skin),
+        })
+    }
+}
+
+fn detect(state: &ModelState, request: &Request, defaults: &DetectOptions) -> Response {
+    let options = match defaults.with_query(request) {
+        Ok(options) => options,
+        Err(response) => return response,
+    };
+
+    let upload = match request.upload() {
+        Some(upload) if !upload.is_empty() => upload,
+        _ => return Response::error(400, "Missing image"),
+    };
+
+    let img = match image::load_from_memory(upload) {
+        Ok(img) => img,
+        Err(_) => return Response::error(415, "The upload is not an image we can decode"),
+    };
+
+    // gray uploads have no colour to filter on
+    let colour = match img {
+        DynamicImage::ImageLuma8(_) |
+        DynamicImage::ImageLumaA8(_) => None,
+        _ => Some(img.to_rgb()),
+    };
+
+    let data = to_gray_image(img);
+    let candidates = if options.skin {
+        Prefilter::Skin.mask(&data, colour.as_ref())
+    } else {
+        None
+    };
+    let params = &options.params;
+
+    let loaded = state.current();
+    let windows = scan(&loaded.model, &data, candidates.as_ref(), params);
+    let detections = params.keep_best(group_detections(&windows, 0.3, options.min_neighbours));
+
+    let response = DetectResponse {
+        width: data.width(),
+        height: data.height(),
+        detections: detections,
+    };
+
+    Response::ok(serde_json::to_string(&response).unwrap())
+}
+
+fn handle(stream: TcpStream, state: &ModelState, defaults: &DetectOptions) {
+    let mut connection = match Connection::new(stream, Duration::from_secs(TIMEOUT_SECONDS)) {
+        Ok(connection) => connection,
+        Err(_) => return,
+    };
+
+    let response = match Request::read(&mut connection) {
+        Ok(request) => {
+            match (request.method.as_str(), request.path.as_str()) {
+                ("GET", "/health") => Response::ok("{\"status\":\"ok\"}".to_string()),
+                ("GET", "/model") => model_info(state),
+                ("POST", "/detect") => detect(state, &request, defaults),
+                (_, "/health") | (_, "/model") | (_, "/detect") => {
+                    Response::error(405, "Method not allowed")
+                }
+                _ => Response::error(404, "Not found"),
+            }
+        }
+        Err(response) => response,
+    };
+
+    // the client may be gone already, nothing to do then
+    let _ = response.write_to(&mut connection);
+}
+
+// counts a connection as handled until it is dropped, even by a panic
+struct ActiveConnection(Arc<AtomicUsize>);
+
+impl Drop for ActiveConnection {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// answers each connection on a thread of its own, forever
+fn serve(listener: TcpListener, state: Arc<ModelState>, defaults: Arc<DetectOptions>) {
+    let active = Arc::new(AtomicUsize::new(0));
+
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(_) => continue,
+        };
+
+        if active.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            active.fetch_sub(1, Ordering::SeqCst);
+
+            let _ = stream.set_write_timeout(Some(Duration::from_secs(TIMEOUT_SECONDS)));
+            let _ = Response::error(503, "Too many connections").write_to(&mut stream);
+            continue;
+        }
+
+        let guard = ActiveConnection(active.clone());
+        let (state, defaults) = (state.clone(), defaults.clone());
+        thread::spawn(move || {
+            let _guard = guard;
+            handle(stream, &state, &defaults)
+        });
+    }
+}
+
+fn main() {
+    let model_path = args::get_option("model").unwrap_or("./foo.json".to_string());
+    let address = args::get_option("address").unwrap_or("127.0.0.1:8080".to_string());
+    let defaults = Arc::new(DetectOptions::from_args());
+
+    let state = Arc::new(ModelState::load(&model_path));
+    let listener = TcpListener::bind(&address).expect("Unable to bind address");
+
+    println!("Serving {} on http://{}", model_path, address);
+
+    serve(listener, state, defaults);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::io::{Cursor, Read, Write};
+    use std::net::SocketAddr;
+    use std::process;
+    use image::{ColorType, GrayImage};
+    use image::png::PNGEncoder;
+    use serde_json::Value;
+    use decision_tree::{DecisionNode, DecisionTree};
+    use geometry::Rect;
+    use prediction_ensemble::PredictionEnsemble;
+    use weak_classifier::WeakClassifier;
+
+    fn request(target: &str) -> Request {
+        let raw = format!("POST {} HTTP/1.1\r\n\r\n", target);
+        Request::read(&mut Cursor::new(raw.into_bytes())).unwrap()
+    }
+
+    #[test]
+    fn query_sizes_are_checked() {
+        let defaults = DetectOptions {
+            params: DetectionParams::default(),
+            min_neighbours: 3,
+            skin: false,
+        };
+
+        let options = defaults.with_query(&request("/detect?min_size=24&max_size=48")).unwrap();
+        assert_eq!((options.params.min_size, options.params.max_size), (24, Some(48)));
+
+        for target in &["/detect?min_size=1", "/detect?min_size=40&max_size=30",
+                        "/detect?min_size=x"] {
+            assert_eq!(defaults.with_query(&request(target)).unwrap_err().status, 400);
+        }
+    }
+
+    // a server on a free port of the loopback, with a model of a single stage
+    // that accepts every window, read from `name` in the temporary directory
+    fn start_server(name: &str) -> SocketAddr {
+        let mut ensemble = PredictionEnsemble::new();
+        ensemble.push(vec![WeakClassifier::Tree(DecisionTree::new(DecisionNode::Leaf {
+                               label: 1.0,
+                           }))]);
+
+        let path = env::temp_dir().join(format!("{}-{}.json", name, process::id()));
+        let path = path.to_str().unwrap();
+        Model::Cascade(ensemble).save(path);
+
+        let defaults = Arc::new(DetectOptions {
+            params: DetectionParams::default(),
+            min_neighbours: 1,
+            skin: false,
+        });
+        let state = Arc::new(ModelState::load(path));
+        // a missing file keeps the loaded model
+        fs::remove_file(path).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        thread::spawn(move || serve(listener, state, defaults));
+
+        address
+    }
+
+    // status and body of the answer to `raw`
+    fn exchange(address: SocketAddr, raw: &[u8]) -> (u16, String) {
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+        stream.write_all(raw).unwrap();
+
+        let mut answer = String::new();
+        stream.read_to_string(&mut answer).unwrap();
+
+        let status = answer.split_whitespace().nth(1).unwrap().parse().unwrap();
+        let body = answer.find("\r\n\r\n").map_or("", |i| &answer[i + 4..]).to_string();
+
+        (status, body)
+    }
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = GrayImage::from_fn(width, height, |x, y| image::Luma([(x * 10 + y) as u8]));
+        let mut encoded = Vec::new();
+        PNGEncoder::new(&mut encoded).encode(&image, width, height, ColorType::Gray(8)).unwrap();
+
+        encoded
+    }
+
+    #[test]
+    fn uploads_are_answered_with_their_detections() {
+        let address = start_server("serve-detect");
+        let upload = png(19, 19);
+
+        let mut raw = format!("POST /detect HTTP/1.1\r\nContent-Type: image/png\r\n\
+                               Content-Length: {}\r\n\r\n",
+                              upload.len())
+            .into_bytes();
+        raw.extend(upload);
+
+        let (status, body) = exchange(address, &raw);
+        assert_eq!(status, 200, "{}", body);
+
+        let answer: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!((answer["width"].as_u64(), answer["height"].as_u64()), (Some(19), Some(19)));
+
+        // the only 19x19 window of the picture
+        let detections = answer["detections"].as_array().unwrap();
+        assert_eq!(detections.len(), 1);
+        assert_eq!(detections[0]["rect"],
+                   serde_json::to_value(Rect::new(0, 0, 19, 19)).unwrap());
+        assert_eq!(detections[0]["neighbours"].as_u64(), Some(1));
+    }
+
+    #[test]
+    fn oversized_and_malformed_requests_are_refused() {
+        let address = start_server("serve-refuse");
+
+        let oversized = b"POST /detect HTTP/1.1\r\nContent-Length: 1000000000\r\n\r\n";
+        let (status, body) = exchange(address, oversized);
+        assert_eq!(status, 413, "{}", body);
+        assert!(body.contains("Upload too large"));
+
+        let (status, _) = exchange(address, b"GARBAGE\r\n\r\n");
+        assert_eq!(status, 400);
+
+        let (status, _) = exchange(address,
+                                   b"POST /detect HTTP/1.1\r\nContent-Length: lots\r\n\r\n");
+        assert_eq!(status, 400);
+
+        let (status, body) = exchange(address, b"POST /detect HTTP/1.1\r\n\r\n");
+        assert_eq!((status, body.contains("Missing image")), (400, true));
+
+        // the server is still up
+        assert_eq!(exchange(address, b"GET /health HTTP/1.1\r\n\r\n").0, 200);
+    }
+}
@@ -0,0 +1,187 @@
+use integral_image::IntegralImage;
+use weak_classifier::WeakClassifier;
+use detector::Detector;
+use compiled_cascade::{CompiledCascade, WindowLayout};
+use shared::{DataPoint, ImageData};
+
+// slack below the calibrated thresholds, so that the faces sitting exactly on
+// them are still accepted after the model goes through its json file
+const THRESHOLD_TOLERANCE: f64 = 1e-9;
+
+// one long boosted sequence with a rejection threshold after every weak classifier
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SoftCascade {
+    classifiers: Vec<WeakClassifier>,
+    rejection_thresholds: Vec<f64>,
+}
+
+impl SoftCascade {
+    // direct backward pruning: keep the faces the whole sequence accepts at the
+    // target detection rate, then reject after each classifier anything scoring
+    // below the lowest partial score of those faces. panics without any weak
+    // classifier, e.g. after compression dropped them all, or without any face
+    pub fn calibrate(classifiers: Vec<WeakClassifier>,
+                     image_collection: &Vec<DataPoint>,
+                     target_detection_rate: f64)
+                     -> SoftCascade {
+        assert!(!classifiers.is_empty(),
+                "A soft cascade needs at least one weak classifier to calibrate");
+        assert!(image_collection.iter().any(|data_point| data_point.label > 0.0),
+                "A soft cascade is calibrated on faces, but the windows hold none");
+        assert!(target_detection_rate > 0.0 && target_detection_rate <= 1.0,
+                "The detection rate must be in (0, 1], got {}",
+                target_detection_rate);
+
+        let partial_scores: Vec<Vec<f64>> = image_collection.iter()
+            .filter(|data_point| data_point.label > 0.0)
+            .map(|data_point| {
+                classifiers.iter()
+                    .scan(0.0, |acc, h| {
+                        *acc += h.predict(&data_point.integral_image);
+                        Some(*acc)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut final_scores: Vec<f64> = partial_scores.iter()
+            .map(|scores| *scores.last().unwrap())
+            .collect();
+        final_scores.sort_by(|a, b| {
+            b.partial_cmp(a).expect("A weak classifier scores a face as NaN")
+        });
+
+        let num_kept = ((final_scores.len() as f64) * target_detection_rate).ceil() as usize;
+        let final_threshold = final_scores[num_kept.max(1).min(final_scores.len()) - 1];
+
+        let kept: Vec<_> = partial_scores.iter()
+            .filter(|scores| *scores.last().unwrap() >= final_threshold)
+            .collect();
+
+        let mut rejection_thresholds: Vec<f64> = (0..classifiers.len())
+            .map(|t| kept.iter().fold(::std::f64::INFINITY, |acc, scores| acc.min(scores[t])))
+            .map(|threshold| threshold - THRESHOLD_TOLERANCE)
+            .collect();
+
+        // the last threshold is the decision threshold of the whole sequence
+        *rejection_thresholds.last_mut().unwrap() = final_threshold - THRESHOLD_TOLERANCE;
+
+        SoftCascade {
+            classifiers: classifiers,
+            rejection_thresholds: rejection_thresholds,
+        }
+    }
+
+    pub fn classifiers(&self) -> &Vec<WeakClassifier> {
+        &self.classifiers
+    }
+
+    pub fn into_classifiers(self) -> Vec<WeakClassifier> {
+        self.classifiers
+    }
+
+    pub fn rejection_thresholds(&self) -> &Vec<f64> {
+        &self.rejection_thresholds
+    }
+
+    // number of weak classifiers evaluated before the window is decided
+    pub fn evaluate(&self, integral_image: &IntegralImage) -> (bool, usize) {
+        let mut score = 0.0;
+
+        for (t, (h, &threshold)) in self.classifiers
+            .iter()
+            .zip(self.rejection_thresholds.iter())
+            .enumerate() {
+            score += h.predict(integral_image);
+
+            if score < threshold {
+                return (false, t + 1);
+            }
+        }
+
+        (true, self.classifiers.len())
+    }
+
+    // margin over the decision threshold of a window the sequence accepts
+    pub fn score(&self, integral_image: &IntegralImage) -> Option<f64> {
+        let mut score = 0.0;
+
+        for (h, &threshold) in self.classifiers.iter().zip(self.rejection_thresholds.iter()) {
+            score += h.predict(integral_image);
+
+            if score < threshold {
+                return None;
+            }
+        }
+
+        Some(score - self.rejection_thresholds.last().map_or(0.0, |&threshold| threshold))
+    }
+
+    pub fn predict(&self, integral_image: &IntegralImage) -> bool {
+        self.evaluate(integral_image).0
+    }
+}
+
+impl Detector for SoftCascade {
+    fn predict_window(&self, _: &ImageData, integral_image: &IntegralImage) -> bool {
+        self.predict(integral_image)
+    }
+
+    fn score_window(&self, _: &ImageData, integral_image: &IntegralImage) -> Option<f64> {
+        self.score(integral_image)
+    }
+
+    fn compile(&self, layout: &WindowLayout) -> Option<CompiledCascade> {
+        Some(CompiledCascade::from_soft_cascade(&self.classifiers, &self.rejection_thresholds, layout))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rulinalg::matrix::Matrix;
+    use decision_tree::{DecisionNode, DecisionTree};
+    use shared::{WINDOW_HEIGHT, WINDOW_WIDTH};
+
+    // a weak classifier voting `label` for every window
+    fn constant(label: f64) -> WeakClassifier {
+        WeakClassifier::Tree(DecisionTree::new(DecisionNode::Leaf { label: label }))
+    }
+
+    fn windows(labels: &[f64]) -> Vec<DataPoint> {
+        labels.iter()
+            .map(|&label| {
+                let image_data = Matrix::zeros(WINDOW_HEIGHT, WINDOW_WIDTH);
+                let integral_image = IntegralImage::build(&image_data);
+
+                DataPoint {
+                    image_data: image_data,
+                    integral_image: integral_image,
+                    label: label,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn calibrated_thresholds_accept_the_faces() {
+        let cascade = SoftCascade::calibrate(vec![constant(1.0), constant(-1.0)],
+                                             &windows(&[1.0, -1.0]),
+                                             1.0);
+
+        assert_eq!(cascade.rejection_thresholds().len(), 2);
+        assert!(cascade.predict(&windows(&[1.0])[0].integral_image));
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one weak classifier")]
+    fn calibrating_nothing_is_reported() {
+        SoftCascade::calibrate(Vec::new(), &windows(&[1.0, -1.0]), 0.99);
+    }
+
+    #[test]
+    #[should_panic(expected = "the windows hold none")]
+    fn calibrating_without_faces_is_reported() {
+        SoftCascade::calibrate(vec![constant(1.0)], &windows(&[-1.0, -1.0]), 0.99);
+    }
+}
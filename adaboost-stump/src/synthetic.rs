@@ -0,0 +1,306 @@
+use image::{GrayImage, Luma};
+use rand::{Rng, SeedableRng, StdRng};
+use rulinalg::matrix::Matrix;
+use integral_image::IntegralImage;
+use geometry::Rect;
+use shared::{DataPoint, ImageData, Label, WINDOW_HEIGHT, WINDOW_WIDTH};
+
+// side of the textured tiles behind the faces of a scene
+const TILE_SIZE: u32 = 40;
+
+pub fn seeded_rng(seed: usize) -> StdRng {
+    let seed: &[_] = &[seed];
+    SeedableRng::from_seed(seed)
+}
+
+fn to_pixel(value: f64) -> Luma<u8> {
+    Luma([value.max(0.0).min(255.0).round() as u8])
+}
+
+// noise, a gradient, stripes, a checkerboard or a single dark blob, with
+// levels and sizes drawn from `rng`
+pub fn texture<R: Rng>(rng: &mut R, width: u32, height: u32) -> GrayImage {
+    let low = rng.gen_range(20.0, 120.0);
+    let high = rng.gen_range(low + 30.0, 240.0);
+    let noise = rng.gen_range(5.0, 25.0);
+
+    let kind = rng.gen_range(0, 5);
+    let period = rng.gen_range(2, 12);
+    let vertical = rng.gen();
+    let angle: f64 = rng.gen_range(0.0, 6.3);
+    let (blob_x, blob_y) = (rng.gen_range(0.0, width as f64), rng.gen_range(0.0, height as f64));
+    let blob_radius = rng.gen_range(2.0, 8.0);
+
+    let mut image = GrayImage::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let (fx, fy) = (x as f64, y as f64);
+
+            let value = match kind {
+                0 => rng.gen_range(low, high),
+                1 => {
+                    let t = (fx * angle.cos() + fy * angle.sin()) / (width + height) as f64;
+                    low + (high - low) * (t + 1.0) / 2.0
+                }
+                2 => {
+                    let position = if vertical { x } else { y };
+                    if (position / period) % 2 == 0 { low } else { high }
+                }
+                3 => if (x / period + y / period) % 2 == 0 { low } else { high },
+                _ => {
+                    let distance = ((fx - blob_x).powi(2) + (fy - blob_y).powi(2)).sqrt();
+                    if distance < blob_radius { low } else { high }
+                }
+            };
+
+            image.put_pixel(x, y, to_pixel(value + rng.gen_range(-noise, noise)));
+        }
+    }
+
+    image
+}
+
+// a bright oval filling `rect` with two dark eyes and a dark mouth, whose
+// places and levels vary a little from face to face
+pub fn draw_face<R: Rng>(rng: &mut R, image: &mut GrayImage, rect: &Rect) {
+    let skin = rng.gen_range(160.0, 230.0);
+    let eye = rng.gen_range(20.0, 60.0);
+    let mouth = rng.gen_range(30.0, 70.0);
+    let noise = rng.gen_range(3.0, 10.0);
+
+    let eye_y = rng.gen_range(0.32, 0.4);
+    let eye_dx = rng.gen_range(0.18, 0.24);
+    let mouth_y = rng.gen_range(0.7, 0.78);
+
+    let inside = |u: f64, v: f64, cx: f64, cy: f64, rx: f64, ry: f64| {
+        ((u - cx) / rx).powi(2) + ((v - cy) / ry).powi(2) <= 1.0
+    };
+
+    for y in 0..rect.height {
+        for x in 0..rect.width {
+            let u = (x as f64 + 0.5) / rect.width as f64;
+            let v = (y as f64 + 0.5) / rect.height as f64;
+
+            // the corners keep the background
+            if !inside(u, v, 0.5, 0.5, 0.5, 0.5) {
+                continue;
+            }
+
+            let value = if inside(u, v, 0.5 - eye_dx, eye_y, 0.12, 0.08) ||
+                           inside(u, v, 0.5 + eye_dx, eye_y, 0.12, 0.08) {
+                eye
+            } else if inside(u, v, 0.5, mouth_y, 0.2, 0.07) {
+                mouth
+            } else {
+                skin
+            };
+
+            image.put_pixel((rect.x + x) as u32,
+                            (rect.y + y) as u32,
+                            to_pixel(value + rng.gen_range(-noise, noise)));
+        }
+    }
+}
+
+// the face fills 16 to 19 pixels of the window, as windows a scale step
+// apart from the face still frame it
+pub fn face_window<R: Rng>(rng: &mut R) -> GrayImage {
+    let mut window = texture(rng, WINDOW_WIDTH as u32, WINDOW_HEIGHT as u32);
+
+    let size = rng.gen_range(WINDOW_WIDTH - 3, WINDOW_WIDTH + 1);
+    let face = Rect::new(rng.gen_range(0, WINDOW_WIDTH - size + 1),
+                         rng.gen_range(0, WINDOW_HEIGHT - size + 1),
+                         size,
+                         size);
+    draw_face(rng, &mut window, &face);
+
+    window
+}
+
+// a texture, or half of the time a face that is too small, too large or off
+// centre, so that windows on parts of a face are rejected
+pub fn non_face_window<R: Rng>(rng: &mut R) -> GrayImage {
+    let mut window = texture(rng, WINDOW_WIDTH as u32, WINDOW_HEIGHT as u32);
+
+    if rng.gen() {
+        let size = if rng.gen() {
+            rng.gen_range(WINDOW_WIDTH / 3, WINDOW_WIDTH * 2 / 3)
+        } else {
+            rng.gen_range(WINDOW_WIDTH * 3 / 2, WINDOW_WIDTH * 3)
+        };
+
+        // drawn on a larger canvas so that the face can stick out of the window
+        let margin = size;
+        let mut canvas = texture(rng,
+                                 (WINDOW_WIDTH + 2 * margin) as u32,
+                                 (WINDOW_HEIGHT + 2 * margin) as u32);
+        let face = Rect::new(rng.gen_range(margin / 2, WINDOW_WIDTH + margin - size / 2),
+                             rng.gen_range(margin / 2, WINDOW_HEIGHT + margin - size / 2),
+                             size,
+                             size);
+        draw_face(rng, &mut canvas, &face);
+
+        for (x, y, pixel) in window.enumerate_pixels_mut() {
+            *pixel = *canvas.get_pixel(x + margin as u32, y + margin as u32);
+        }
+    }
+
+    window
+}
+
+// pixels over the brightest one, as the loaders and the scanner do
+fn build_data_point(window: &GrayImage, label: Label) -> DataPoint {
+    let max = window.iter().fold(1.0, |acc: f64, &value| acc.max(value as f64));
+    let mut image_data: ImageData = Matrix::zeros(window.height() as usize,
+                                                  window.width() as usize);
+
+    for (x, y, pixel) in window.enumerate_pixels() {
+        image_data[[y as usize, x as usize]] = pixel.data[0] as f64 / max;
+    }
+
+    let integral_image = IntegralImage::build(&image_data);
+
+    DataPoint {
+        image_data: image_data,
+        integral_image: integral_image,
+        label: label,
+    }
+}
+
+// faces first then non-faces, the same for a given seed, counted as
+// get_data counts them
+pub fn synthetic_dataset(seed: usize,
+                         num_faces: usize,
+                         num_non_faces: usize)
+                         -> (Vec<DataPoint>, usize, usize) {
+    let mut rng = seeded_rng(seed);
+
+    let faces: Vec<_> = (0..num_faces)
+        .map(|_| build_data_point(&face_window(&mut rng), 1.0))
+        .collect();
+    let non_faces: Vec<_> = (0..num_non_faces)
+        .map(|_| build_data_point(&non_face_window(&mut rng), -1.0))
+        .collect();
+
+    (faces.into_iter().chain(non_faces.into_iter()).collect(), num_faces, num_non_faces)
+}
+
+// a picture of textured tiles with faces drawn at known places
+#[derive(Debug)]
+pub struct Scene {
+    pub image: GrayImage,
+    pub faces: Vec<Rect>,
+}
+
+// up to `num_faces` square faces between `min_size` and `max_size` pixels,
+// kept apart by half their size; a face that does not fit is left out
+pub fn synthetic_scene(seed: usize,
+                       width: u32,
+                       height: u32,
+                       num_faces: usize,
+                       min_size: usize,
+                       max_size: usize)
+                       -> Scene {
+    let mut rng = seeded_rng(seed);
+
+    let mut image = GrayImage::new(width, height);
+    for tile_y in 0..(height + TILE_SIZE - 1) / TILE_SIZE {
+        for tile_x in 0..(width + TILE_SIZE - 1) / TILE_SIZE {
+            let tile = texture(&mut rng, TILE_SIZE, TILE_SIZE);
+
+            for (x, y, &pixel) in tile.enumerate_pixels() {
+                let (x, y) = (tile_x * TILE_SIZE + x, tile_y * TILE_SIZE + y);
+                if x < width && y < height {
+                    image.put_pixel(x, y, pixel);
+                }
+            }
+        }
+    }
+
+    let mut faces: Vec<Rect> = Vec::new();
+    for _ in 0..num_faces {
+        let size = rng.gen_range(min_size, max_size + 1);
+        if size > width as usize || size > height as usize {
+            continue;
+        }
+
+        for _ in 0..100 {
+            let rect = Rect::new(rng.gen_range(0, width as usize - size + 1),
+                                 rng.gen_range(0, height as usize - size + 1),
+                                 size,
+                                 size);
+            let margin = size / 2;
+            let grown = Rect::new(rect.x.saturating_sub(margin),
+                                  rect.y.saturating_sub(margin),
+                                  size + 2 * margin,
+                                  size + 2 * margin);
+
+            if faces.iter().all(|face| !face.intersects(&grown)) {
+                draw_face(&mut rng, &mut image, &rect);
+                faces.push(rect);
+                break;
+            }
+        }
+    }
+
+    Scene {
+        image: image,
+        faces: faces,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // mean of the pixels of `window` under `rect`
+    fn mean(window: &GrayImage, rect: &Rect) -> f64 {
+        let sum = (rect.y..rect.bottom())
+            .flat_map(|y| (rect.x..rect.right()).map(move |x| (x, y)))
+            .fold(0.0, |acc, (x, y)| acc + window.get_pixel(x as u32, y as u32).data[0] as f64);
+
+        sum / rect.area() as f64
+    }
+
+    #[test]
+    fn faces_have_dark_eyes_and_mouth() {
+        let mut rng = seeded_rng(1);
+
+        // large enough that these boxes stay on the features however they move
+        for _ in 0..50 {
+            let mut face = texture(&mut rng, 40, 40);
+            draw_face(&mut rng, &mut face, &Rect::new(0, 0, 40, 40));
+            let cheeks = mean(&face, &Rect::new(12, 20, 16, 4));
+
+            assert!(mean(&face, &Rect::new(10, 13, 3, 3)) < cheeks);
+            assert!(mean(&face, &Rect::new(27, 13, 3, 3)) < cheeks);
+            assert!(mean(&face, &Rect::new(18, 29, 4, 2)) < cheeks);
+        }
+    }
+
+    #[test]
+    fn datasets_are_reproducible() {
+        let (a, num_faces, num_non_faces) = synthetic_dataset(7, 10, 20);
+        let (b, _, _) = synthetic_dataset(7, 10, 20);
+
+        assert_eq!((a.len(), num_faces, num_non_faces), (30, 10, 20));
+        assert!(a.iter().take(10).all(|data_point| data_point.label > 0.0));
+        assert!(a.iter().skip(10).all(|data_point| data_point.label < 0.0));
+        assert!(a.iter().zip(b.iter()).all(|(a, b)| a.image_data == b.image_data));
+        assert!(a.iter().all(|data_point| data_point.image_data.data().iter().all(|&v| v <= 1.0)));
+    }
+
+    #[test]
+    fn scene_faces_fit_and_stay_apart() {
+        let scene = synthetic_scene(3, 200, 150, 4, 20, 40);
+
+        assert_eq!(scene.image.dimensions(), (200, 150));
+        assert!(!scene.faces.is_empty());
+
+        for (i, face) in scene.faces.iter().enumerate() {
+            assert!(face.right() <= 200 && face.bottom() <= 150);
+            assert!(face.width >= 20 && face.width <= 40 && face.width == face.height);
+            assert!(scene.faces[i + 1..].iter().all(|other| !other.intersects(face)));
+        }
+    }
+}
@@ -0,0 +1,113 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_json;
+extern crate image;
+extern crate rulinalg;
+extern crate rand;
+
+mod load;
+mod frame_source;
+mod pgm;
+mod manifest;
+mod synthetic;
+mod shared;
+mod geometry;
+mod integral_image;
+mod haar_like_feature;
+mod decision_tree;
+mod lbp_feature;
+mod weak_classifier;
+mod prediction_ensemble;
+mod soft_cascade;
+mod detector;
+mod fast_integral_image;
+mod compiled_cascade;
+mod linear_svm;
+mod hog;
+mod model;
+mod detection_params;
+mod warp;
+mod prefilter;
+mod scanner;
+mod annotations;
+mod tracker;
+mod args;
+
+use std::fs::File;
+use std::io::Write;
+use frame_source::{Frame, FrameSource};
+use model::Model;
+use detection_params::DetectionParams;
+use prefilter::Prefilters;
+use scanner::{group_detections, scan};
+use tracker::{TrackedBox, Tracker};
+
+#[derive(Serialize, Debug)]
+struct FrameTracks {
+    frame: usize,
+    name: String,
+    tracks: Vec<TrackedBox>,
+}
+
+fn main() {
+    let frames_spec = args::get_option::<String>("frames").expect("Missing --frames INPUT");
+    let model_path = args::get_option("model").unwrap_or("./foo.json".to_string());
+    let output = args::get_option("output").unwrap_or("tracks.json".to_string());
+
+    let min_overlap = args::get_option("min-overlap").unwrap_or(0.3);
+    let min_neighbours = args::get_option("min-neighbours").unwrap_or(3);
+    let max_misses = args::get_option("max-misses").unwrap_or(5);
+    let min_hits = args::get_option("min-hits").unwrap_or(3);
+
+    let model = Model::load(&model_path);
+    let params = DetectionParams::from_args();
+    let mut prefilters = Prefilters::from_args();
+    let mut tracker = Tracker::new(min_overlap, max_misses, min_hits);
+
+    let raw_size = args::get_option("width").and_then(|width| {
+        args::get_option("height").map(|height| (width, height))
+    });
+
+    let mut frames = Vec::new();
+
+    let frame_source = FrameSource::open(&frames_spec, raw_size);
+
+    for (frame, Frame { name, gray: data, colour, error }) in frame_source.enumerate() {
+        // an undecodable frame counts as a frame without detections, so that
+        // the tracks coast through it and the frame numbers stay aligned
+        let detections: Vec<_> = match error {
+            Some(error) => {
+                println!("Frame {}: unable to decode, {}", frame, error);
+                Vec::new()
+            }
+            None => {
+                let candidates = prefilters.mask(&data, colour.as_ref());
+                let windows = scan(&model, &data, candidates.as_ref(), &params);
+
+                params.keep_best(group_detections(&windows, min_overlap, min_neighbours))
+                    .iter()
+                    .map(|detection| detection.rect)
+                    .collect()
+            }
+        };
+
+        let tracks = tracker.update(&detections);
+
+        println!("Frame {}: {} detections, {} tracks",
+                 frame,
+                 detections.len(),
+                 tracks.len());
+
+        frames.push(FrameTracks {
+            frame: frame,
+            name: name,
+            tracks: tracks,
+        });
+    }
+
+    let serialized = serde_json::to_string_pretty(&frames).unwrap();
+
+    let mut f = File::create(&output).expect("Unable to create file");
+    f.write_all(serialized.as_bytes()).expect("Unable to write data");
+}
@@ -0,0 +1,259 @@
+use rulinalg::matrix::{BaseMatrix, Matrix};
+use rulinalg::vector::Vector;
+use geometry::Rect;
+
+// the state is the box center, its size and their velocities
+const STATE_SIZE: usize = 8;
+const MEASUREMENT_SIZE: usize = 4;
+
+// constant velocity kalman filter over (center x, center y, width, height)
+#[derive(Debug, Clone)]
+struct KalmanBox {
+    state: Vector<f64>,
+    covariance: Matrix<f64>,
+}
+
+impl KalmanBox {
+    fn new(rect: &Rect) -> KalmanBox {
+        let mut state = vec![0.0; STATE_SIZE];
+        state[..MEASUREMENT_SIZE].copy_from_slice(&KalmanBox::measure(rect));
+
+        // positions are known, velocities are not
+        let uncertainty: Vec<f64> = (0..STATE_SIZE)
+            .map(|i| if i < MEASUREMENT_SIZE { 10.0 } else { 1000.0 })
+            .collect();
+
+        KalmanBox {
+            state: Vector::new(state),
+            covariance: Matrix::from_diag(&uncertainty),
+        }
+    }
+
+    fn measure(rect: &Rect) -> [f64; MEASUREMENT_SIZE] {
+        [rect.x as f64 + rect.width as f64 / 2.0,
+         rect.y as f64 + rect.height as f64 / 2.0,
+         rect.width as f64,
+         rect.height as f64]
+    }
+
+    fn transition() -> Matrix<f64> {
+        Matrix::from_fn(STATE_SIZE, STATE_SIZE, |col, row| {
+            if row == col || col == row + MEASUREMENT_SIZE {
+                1.0
+            } else {
+                0.0
+            }
+        })
+    }
+
+    fn observation() -> Matrix<f64> {
+        Matrix::from_fn(MEASUREMENT_SIZE,
+                        STATE_SIZE,
+                        |col, row| if row == col { 1.0 } else { 0.0 })
+    }
+
+    fn predict(&mut self) {
+        let transition = KalmanBox::transition();
+        let process_noise: Vec<f64> = (0..STATE_SIZE)
+            .map(|i| if i < MEASUREMENT_SIZE { 1.0 } else { 0.01 })
+            .collect();
+
+        self.state = &transition * &self.state;
+        self.covariance = &transition * &self.covariance * transition.transpose() +
+                          Matrix::from_diag(&process_noise);
+    }
+
+    fn update(&mut self, rect: &Rect) {
+        let observation = KalmanBox::observation();
+        let measurement_noise = Matrix::from_diag(&[1.0, 1.0, 10.0, 10.0]);
+
+        let innovation = Vector::new(KalmanBox::measure(rect).to_vec()) -
+                         &observation * &self.state;
+        let innovation_covariance = &observation * &self.covariance * observation.transpose() +
+                                    measurement_noise;
+        let gain = &self.covariance * observation.transpose() *
+                   innovation_covariance.inverse().expect("Singular innovation covariance");
+
+        self.state = &self.state + &gain * innovation;
+        self.covariance = (Matrix::identity(STATE_SIZE) - &gain * &observation) *
+                          &self.covariance;
+    }
+
+    fn rect(&self) -> Rect {
+        let width = self.state[2].max(1.0);
+        let height = self.state[3].max(1.0);
+
+        Rect::new((self.state[0] - width / 2.0).max(0.0).round() as usize,
+                  (self.state[1] - height / 2.0).max(0.0).round() as usize,
+                  width.round() as usize,
+                  height.round() as usize)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Track {
+    id: usize,
+    filter: KalmanBox,
+    hits: usize,
+    misses: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone)]
+pub struct TrackedBox {
+    pub id: usize,
+    pub rect: Rect,
+    // true when the box was not detected in this frame and comes from the filter
+    pub predicted: bool,
+}
+
+pub struct Tracker {
+    tracks: Vec<Track>,
+    next_id: usize,
+    min_overlap: f64,
+    max_misses: usize,
+    min_hits: usize,
+}
+
+impl Tracker {
+    // tracks are reported once detected `min_hits` times and kept alive for
+    // `max_misses` frames without a matching detection
+    pub fn new(min_overlap: f64, max_misses: usize, min_hits: usize) -> Tracker {
+        Tracker {
+            tracks: Vec::new(),
+            next_id: 1,
+            min_overlap: min_overlap,
+            max_misses: max_misses,
+            min_hits: min_hits,
+        }
+    }
+
+    // feeds the detections of the next frame and returns the live tracks
+    pub fn update(&mut self, detections: &Vec<Rect>) -> Vec<TrackedBox> {
+        for track in self.tracks.iter_mut() {
+            track.filter.predict();
+        }
+
+        // greedy association, best overlapping pairs first
+        let mut pairs = Vec::new();
+        for (t, track) in self.tracks.iter().enumerate() {
+            let predicted = track.filter.rect();
+            for (d, detection) in detections.iter().enumerate() {
+                let overlap = predicted.overlap(detection);
+                if overlap >= self.min_overlap {
+                    pairs.push((overlap, t, d));
+                }
+            }
+        }
+        pairs.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+
+        let mut track_matched = vec![false; self.tracks.len()];
+        let mut detection_matched = vec![false; detections.len()];
+
+        for &(_, t, d) in pairs.iter() {
+            if track_matched[t] || detection_matched[d] {
+                continue;
+            }
+
+            track_matched[t] = true;
+            detection_matched[d] = true;
+
+            let ref mut track = self.tracks[t];
+            track.filter.update(&detections[d]);
+            track.hits += 1;
+            track.misses = 0;
+        }
+
+        for (track, &matched) in self.tracks.iter_mut().zip(track_matched.iter()) {
+            if !matched {
+                track.misses += 1;
+            }
+        }
+
+        let max_misses = self.max_misses;
+        self.tracks.retain(|track| track.misses <= max_misses);
+
+        for (detection, &matched) in detections.iter().zip(detection_matched.iter()) {
+            if !matched {
+                self.tracks.push(Track {
+                    id: self.next_id,
+                    filter: KalmanBox::new(detection),
+                    hits: 1,
+                    misses: 0,
+                });
+                self.next_id += 1;
+            }
+        }
+
+        self.tracks
+            .iter()
+            .filter(|track| track.hits >= self.min_hits)
+            .map(|track| {
+                TrackedBox {
+                    id: track.id,
+                    rect: track.filter.rect(),
+                    predicted: track.misses > 0,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a 40 pixel box moving 5 pixels right and 2 down every frame
+    fn moving_box(frame: usize) -> Rect {
+        Rect::new(20 + 5 * frame, 30 + 2 * frame, 40, 40)
+    }
+
+    #[test]
+    fn a_box_at_constant_speed_keeps_its_track() {
+        let mut tracker = Tracker::new(0.3, 2, 3);
+
+        let mut ids = Vec::new();
+        for frame in 0..20 {
+            let tracks = tracker.update(&vec![moving_box(frame)]);
+            assert!(tracks.len() <= 1);
+            ids.extend(tracks.iter().map(|track| track.id));
+        }
+
+        // reported from the third hit on, always as the same track
+        assert_eq!(ids, vec![1; 18]);
+    }
+
+    #[test]
+    fn a_missed_frame_is_bridged() {
+        let mut tracker = Tracker::new(0.3, 2, 3);
+
+        for frame in 0..10 {
+            tracker.update(&vec![moving_box(frame)]);
+        }
+
+        let missed = tracker.update(&Vec::new());
+        assert_eq!(missed.len(), 1);
+        assert_eq!(missed[0].id, 1);
+        assert!(missed[0].predicted);
+        // the filter carried the box along with its velocity
+        assert!(missed[0].rect.overlap(&moving_box(10)) > 0.7);
+
+        let found = tracker.update(&vec![moving_box(11)]);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, 1);
+        assert!(!found[0].predicted);
+    }
+
+    #[test]
+    fn tracks_end_after_too_many_misses() {
+        let mut tracker = Tracker::new(0.3, 2, 1);
+
+        tracker.update(&vec![moving_box(0)]);
+        for _ in 0..3 {
+            tracker.update(&Vec::new());
+        }
+
+        let tracks = tracker.update(&vec![moving_box(4)]);
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(tracks[0].id, 2);
+    }
+}
@@ -10,188 +10,122 @@ extern crate rulinalg;
 extern crate rand;
 
 mod load;
+mod pgm;
+mod manifest;
+mod synthetic;
 mod shared;
+mod geometry;
 mod integral_image;
 mod haar_like_feature;
+mod decision_tree;
+mod lbp_feature;
+mod weak_classifier;
 mod prediction_ensemble;
-
-use std::fs::File;
-use std::io::Write;
-use rand::{thread_rng, Rng};
-use std::iter::Iterator;
-use rulinalg::vector::Vector;
-use load::get_training_data;
+mod soft_cascade;
+mod detector;
+mod fast_integral_image;
+mod compiled_cascade;
+mod linear_svm;
+mod hog;
+mod model;
+mod boosting;
+mod args;
+
+use rand::Rng;
+use load::get_data_from_args;
 use haar_like_feature::HaarLikeFeature;
+use lbp_feature::LbpFeature;
 use prediction_ensemble::PredictionEnsemble;
-use shared::DataPoint;
-
-fn weak_learner(feature_collection: &mut Vec<HaarLikeFeature>,
-                image_collection: &Vec<DataPoint>,
-                weights: &Vector<f64>)
-                -> HaarLikeFeature {
-    let mut error_star = std::f64::INFINITY;
-    let mut fi_star = None;
-    let mut threshold_star = None;
-
-    for fi in 0..(feature_collection.len()) {
-        let ref mut feature_hypothesis = feature_collection[fi];
-
-        let mut scores: Vec<_> = image_collection.iter()
-            .enumerate()
-            .map(|(index, ref data_point)| {
-                (index, feature_hypothesis.get_score(&data_point.integral_image))
-            })
-            .collect();
-
-        scores.sort_by(|&a, &b| a.partial_cmp(&b).unwrap());
-
-        let mut error = image_collection.iter()
-            .zip(weights.iter())
-            .fold(0.0, |acc, (data_point, weight)| {
-                if data_point.label * feature_hypothesis.polarity < 0.0 {
-                    acc + weight
-                } else {
-                    acc
-                }
-            });
-
-        let m = scores.len();
-        for xi in 0..(m + 1) {
-            let curr_x = if xi > 0 {
-                scores[xi - 1].1
-            } else {
-                scores[xi].1 - 1.0
-            };
-
-            let next_x = if xi < m {
-                scores[xi].1
-            } else {
-                scores[xi - 1].1 + 1.0
-            };
-
-            let threshold = (curr_x + next_x) / 2.0;
-            feature_hypothesis.threshold = threshold;
-
-            if error < error_star {
-                error_star = error;
-                fi_star = Some(fi);
-                threshold_star = Some(threshold);
-            }
+use soft_cascade::SoftCascade;
+use model::Model;
+use boosting::{adaboost, FeaturePool};
+use synthetic::seeded_rng;
 
-            if xi < m {
-                // computes the error of the next iteration
-                let ref data_point = image_collection[xi];
-                let weight = weights[xi];
-                error += feature_hypothesis.polarity * data_point.label * weight;
-            } else if error < error_star {
-                // check after all error updates
-                error_star = error;
-                fi_star = Some(fi);
-                threshold_star = Some(threshold);
-            }
+fn main() {
+    let (mut image_collection, num_faces, num_non_faces) =
+        get_data_from_args("./data/trainset", "train");
+
+    // generate all the possible features of the chosen family from the bounding boxes
+    let mut feature_pool = match args::get_option::<String>("features") {
+        Some(ref family) if family == "lbp" => FeaturePool::Lbp(LbpFeature::generate_all_features()),
+        Some(ref family) if family != "haar" => panic!("Unknown feature family: {}", family),
+        _ => FeaturePool::Haar(HaarLikeFeature::generate_all_features()),
+    };
+
+    // a depth of 1 trains the usual decision stumps
+    let tree_depth = args::get_option("depth").unwrap_or(1);
+
+    // false negative to false positive cost; 1 gives symmetric adaboost
+    let cost_ratio = args::get_option("cost-ratio").unwrap_or(1.0);
+    assert!(cost_ratio > 0.0, "--cost-ratio has to be positive, got {}", cost_ratio);
+
+    // features stay in the pool once picked, so later rounds can pick them again
+    let reuse_features = args::get_flag("reuse-features");
+
+    // share of the pool each weak learner searches, drawn anew every round
+    let feature_fraction = args::get_option("feature-fraction").unwrap_or(1.0);
+
+    // the same sampling seed draws the same features and shuffles, so trains the
+    // same model. --seed is left to the synthetic windows
+    let mut rng = seeded_rng(args::get_option("sampling-seed").unwrap_or(0));
+
+    let model = match args::get_option("soft-cascade") {
+        Some(num_rounds) => {
+            let classifiers = adaboost(num_rounds,
+                                       tree_depth,
+                                       cost_ratio,
+                                       reuse_features,
+                                       false,
+                                       feature_fraction,
+                                       &mut feature_pool,
+                                       &image_collection,
+                                       num_faces,
+                                       num_non_faces,
+                                       &mut rng);
+
+            let detection_rate = args::get_option("detection-rate").unwrap_or(0.99);
+            let cascade = SoftCascade::calibrate(classifiers, &image_collection, detection_rate);
+
+            let evaluations = image_collection.iter()
+                .filter(|data_point| data_point.label < 0.0)
+                .fold(0, |acc, data_point| acc + cascade.evaluate(&data_point.integral_image).1);
+            println!("Average weak classifiers evaluated per non-face: {:.2}",
+                     (evaluations as f64) / (num_non_faces as f64));
+
+            Model::SoftCascade(cascade)
         }
-    }
-
-    let mut feature = feature_collection.remove(fi_star.unwrap());
-    feature.threshold = threshold_star.unwrap();
-
-    feature
-}
-
-fn adaboost(num_rounds: usize,
-            mut feature_collection: &mut Vec<HaarLikeFeature>,
-            image_collection: &Vec<DataPoint>,
-            num_faces: usize,
-            num_non_faces: usize)
-            -> Vec<HaarLikeFeature> {
-    let mut weights: Vector<f64> = image_collection.iter()
-        .map(|data_point| {
-            if data_point.label > 0.0 {
-                1.0 / ((2 * num_faces) as f64)
-            } else {
-                1.0 / ((2 * num_non_faces) as f64)
-            }
-        })
-        .collect();
-
-    let mut composition = Vec::new();
-
-    for t in 0..num_rounds {
-        println!("Begun round: {}", t + 1);
-
-        // normalize weights
-        let sum = weights.sum();
-        weights = weights / sum;
-
-        let mut h = weak_learner(&mut feature_collection, image_collection, &weights);
-
-        let label_prediction_tuples: Vec<_> = image_collection.iter()
-            .map(|data_point| (data_point.label, h.predict(&data_point.integral_image)))
-            .collect();
+        None => {
+            let rounds: Vec<usize> = vec![1, 10, 25, 25, 50, 50, 100];
 
-        let epsilon = label_prediction_tuples.iter()
-            .zip(weights.iter())
-            .fold(0.0, |acc, (&(label, prediction), weight)| {
-                if label * prediction < 0.0 {
-                    acc + weight
-                } else {
-                    acc
-                }
-            });
-
-        h.weight = 0.5 * ((1.0 - epsilon) / epsilon).ln();
+            let mut ensemble = PredictionEnsemble::new();
 
-        println!("h({}) = {:?}", t + 1, h);
+            for (i, num_rounds) in rounds.into_iter().enumerate() {
+                // shuffle data to introduce randomness
+                {
 
-        composition.push(h);
-
-        weights = label_prediction_tuples.iter()
-            .zip(weights.iter())
-            .map(|(&(label, prediction), &weight)| {
-                if label * prediction > 0.0 {
-                    weight * (epsilon / (1.0 - epsilon)).sqrt()
-                } else {
-                    weight * ((1.0 - epsilon) / epsilon).sqrt()
+                    let slice = image_collection.as_mut_slice();
+                    rng.shuffle(slice);
                 }
-            })
-            .collect();
-
-        println!("Finished round: {}", t + 1);
-    }
-
-    composition
-}
-
-fn main() {
-    let (mut image_collection, num_faces, num_non_faces) = get_training_data();
 
-    // generate all the possible haar-like features from the bounding boxes and the feature types
-    let mut feature_collection = HaarLikeFeature::generate_all_features();
-
-    let rounds: Vec<usize> = vec![1, 10, 25, 25, 50, 50, 100];
-
-    let mut ensemble = PredictionEnsemble::new();
-
-    for (i, num_rounds) in rounds.into_iter().enumerate() {
-        // shuffle data to introduce randomness
-        {
+                let composition = adaboost(num_rounds,
+                                           tree_depth,
+                                           cost_ratio,
+                                           reuse_features,
+                                           true,
+                                           feature_fraction,
+                                           &mut feature_pool,
+                                           &image_collection,
+                                           num_faces,
+                                           num_non_faces,
+                                           &mut rng);
+
+                ensemble.push(composition);
+                println!("Finished layer {:?}", i + 1);
+            }
 
-            let slice = image_collection.as_mut_slice();
-            thread_rng().shuffle(slice);
+            Model::Cascade(ensemble)
         }
+    };
 
-        let composition = adaboost(num_rounds,
-                                   &mut feature_collection,
-                                   &image_collection,
-                                   num_faces,
-                                   num_non_faces);
-
-        ensemble.push(composition);
-        println!("Finished layer {:?}", i + 1);
-    }
-
-    let serialized = serde_json::to_string(&ensemble).unwrap();
-
-    let mut f = File::create("foo.json").expect("Unable to create file");
-    f.write_all(serialized.as_bytes()).expect("Unable to write data");
+    model.save("foo.json");
 }
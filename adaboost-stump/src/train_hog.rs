@@ -0,0 +1,49 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_json;
+extern crate image;
+extern crate rulinalg;
+extern crate rand;
+
+mod load;
+mod pgm;
+mod manifest;
+mod synthetic;
+mod shared;
+mod geometry;
+mod integral_image;
+mod haar_like_feature;
+mod decision_tree;
+mod lbp_feature;
+mod weak_classifier;
+mod prediction_ensemble;
+mod soft_cascade;
+mod detector;
+mod fast_integral_image;
+mod compiled_cascade;
+mod linear_svm;
+mod hog;
+mod model;
+mod args;
+
+use load::get_data_from_args;
+use hog::{compute_descriptor, HogDetector};
+use linear_svm::LinearSvm;
+use model::Model;
+
+fn main() {
+    let (image_collection, _, _) = get_data_from_args("./data/trainset", "train");
+
+    let samples: Vec<_> = image_collection.iter()
+        .map(|data_point| (compute_descriptor(&data_point.image_data), data_point.label))
+        .collect();
+
+    let lambda = args::get_option("lambda").unwrap_or(1e-4);
+    let num_epochs = args::get_option("epochs").unwrap_or(10);
+
+    let svm = LinearSvm::train(&samples, lambda, num_epochs);
+
+    let output = args::get_option("output").unwrap_or("hog.json".to_string());
+    Model::Hog(HogDetector::new(svm)).save(&output);
+}
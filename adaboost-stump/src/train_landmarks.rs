@@ -0,0 +1,138 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_json;
+extern crate image;
+extern crate rulinalg;
+extern crate rand;
+
+mod load;
+mod pgm;
+mod manifest;
+mod synthetic;
+mod shared;
+mod geometry;
+mod integral_image;
+mod annotations;
+mod warp;
+mod landmarks;
+mod args;
+
+use std::path::Path;
+use rand::{Rng, SeedableRng, StdRng};
+use annotations::{get_faces_map, FaceFeature};
+use geometry::Rect;
+use landmarks::LandmarkRegressor;
+use load::open_gray_image;
+
+// the detector does not frame faces exactly: boxes are shifted and scaled by up
+// to this fraction of their side during training
+const JITTER: f64 = 0.1;
+
+// jittered boxes per annotated face
+const AUGMENTATION: usize = 5;
+
+// every n-th image is held out to measure the error
+const HOLD_OUT: usize = 10;
+
+// the part of a square box that lies right of and below the image origin
+fn to_rect(x: f64, y: f64, size: f64) -> Rect {
+    Rect::new(x.max(0.0) as usize,
+              y.max(0.0) as usize,
+              (size + x.min(0.0)).max(0.0) as usize,
+              (size + y.min(0.0)).max(0.0) as usize)
+}
+
+fn jittered_box<R: Rng>(feature: &FaceFeature, rng: &mut R) -> Rect {
+    let (x, y, size) = feature.face_box();
+    let scale = 1.0 + rng.gen_range(-JITTER, JITTER);
+    let jittered_size = size * scale;
+
+    to_rect(x + rng.gen_range(-JITTER, JITTER) * size - (jittered_size - size) / 2.0,
+            y + rng.gen_range(-JITTER, JITTER) * size - (jittered_size - size) / 2.0,
+            jittered_size)
+}
+
+// mean distance between predicted and true landmarks over the eye distance
+fn normalized_error(predicted: &FaceFeature, truth: &FaceFeature) -> f64 {
+    let error = predicted.to_vec()
+        .iter()
+        .zip(truth.to_vec().iter())
+        .fold(0.0, |acc, (p, t)| acc + (p.0 - t.0).hypot(p.1 - t.1));
+
+    error / (predicted.to_vec().len() as f64) / truth.eye_distance()
+}
+
+fn main() {
+    let annotations = args::get_option("annotations")
+        .unwrap_or("./data/validation/faces.txt".to_string());
+    let images = args::get_option("images").unwrap_or("./data/validation/pics".to_string());
+    let output = args::get_option("output").unwrap_or("landmarks.json".to_string());
+    let seed: usize = args::get_option("seed").unwrap_or(0);
+
+    let mut rng: StdRng = SeedableRng::from_seed(&[seed][..]);
+
+    let mut faces_map: Vec<_> = get_faces_map(&annotations)
+        .into_iter()
+        .filter(|&(ref filename, _)| Path::new(&images).join(filename).exists())
+        .collect();
+    faces_map.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let loaded: Vec<_> = faces_map.iter()
+        .map(|&(ref filename, ref features)| {
+            (open_gray_image(Path::new(&images).join(filename)), features)
+        })
+        .collect();
+
+    let mut training_samples = Vec::new();
+    let mut test_samples = Vec::new();
+
+    for (i, &(ref img, features)) in loaded.iter().enumerate() {
+        for feature in features.iter() {
+            let (x, y, size) = feature.face_box();
+
+            if i % HOLD_OUT == 0 {
+                test_samples.push((img, to_rect(x, y, size), *feature));
+                continue;
+            }
+
+            training_samples.push((img, to_rect(x, y, size), *feature));
+            for _ in 1..AUGMENTATION {
+                training_samples.push((img, jittered_box(feature, &mut rng), *feature));
+            }
+        }
+    }
+
+    assert!(!training_samples.is_empty() && !test_samples.is_empty(),
+            "Every {}th image is held out: {} annotated images are not enough",
+            HOLD_OUT,
+            loaded.len());
+
+    println!("Training on {} samples, testing on {}",
+             training_samples.len(),
+             test_samples.len());
+
+    let regressor = LandmarkRegressor::train(&training_samples, &mut rng);
+
+    let error = test_samples.iter()
+        .fold(0.0, |acc, &(img, rect, ref feature)| {
+            acc + normalized_error(&regressor.predict(img, &rect), feature)
+        });
+    println!("Mean error over the eye distance: {:.4}",
+             error / (test_samples.len() as f64));
+
+    regressor.save(&output);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boxes_are_clipped_at_the_origin() {
+        assert_eq!(to_rect(3.5, 7.0, 20.0), Rect::new(3, 7, 20, 20));
+        assert_eq!(to_rect(-5.0, 2.0, 20.0), Rect::new(0, 2, 15, 20));
+        assert_eq!(to_rect(4.0, -8.0, 20.0), Rect::new(4, 0, 20, 12));
+        assert_eq!(to_rect(-30.0, 0.0, 20.0), Rect::new(0, 0, 0, 20));
+    }
+}
@@ -4,25 +4,53 @@ extern crate serde;
 extern crate serde_json;
 extern crate image;
 extern crate rulinalg;
+extern crate rand;
 extern crate piston_window;
 
 mod shared;
+mod geometry;
 mod integral_image;
 mod prediction_ensemble;
+mod soft_cascade;
 mod haar_like_feature;
-
-use std::fs::File;
-use std::io::Read;
-use image::{ConvertBuffer, DynamicImage, Pixel};
-use rulinalg::matrix::Matrix;
-use integral_image::IntegralImage;
-use shared::{WINDOW_HEIGHT, WINDOW_WIDTH};
-use prediction_ensemble::PredictionEnsemble;
+mod decision_tree;
+mod lbp_feature;
+mod weak_classifier;
+mod detector;
+mod fast_integral_image;
+mod compiled_cascade;
+mod linear_svm;
+mod hog;
+mod model;
+mod detection_params;
+mod prefilter;
+mod scanner;
+mod annotations;
+mod warp;
+mod landmarks;
+mod args;
+
+use image::{ConvertBuffer, DynamicImage};
+use model::Model;
+use detection_params::DetectionParams;
+use prefilter::Prefilters;
+use scanner::{group_detections, scan};
+use landmarks::LandmarkRegressor;
 use piston_window::{PistonWindow, Texture, WindowSettings, TextureSettings};
 use piston_window::rectangle::Rectangle;
 
 fn main() {
-    let data = match image::open("./data/got.jpeg").unwrap() {
+    let image_path = args::get_option("image").unwrap_or("./data/got.jpeg".to_string());
+
+    let img = image::open(image_path).unwrap();
+
+    // the skin filter needs the colours the detector does without
+    let colour = match img {
+        DynamicImage::ImageLuma8(_) => None,
+        _ => Some(img.to_rgb()),
+    };
+
+    let data = match img {
         DynamicImage::ImageLuma8(gray_image) => gray_image,
         DynamicImage::ImageRgb8(rgb_image) => rgb_image.convert(),
         DynamicImage::ImageRgba8(rgba_image) => rgba_image.convert(),
@@ -31,39 +59,25 @@ fn main() {
 
     let (width, height) = data.dimensions();
 
-    let mut model_raw = String::new();
-    let mut f = File::open("./foo.json").expect("Unable to open file");
-    f.read_to_string(&mut model_raw).expect("Unable to read string");
+    let model_path = args::get_option("model").unwrap_or("./foo.json".to_string());
+    let model = Model::load(&model_path);
 
-    let ensemble: PredictionEnsemble = serde_json::from_str(&model_raw).unwrap();
+    let params = DetectionParams::from_args();
+    let candidates = Prefilters::from_args().mask(&data, colour.as_ref());
+    let mut coll = scan(&model, &data, candidates.as_ref(), &params);
 
-    let mut coll = Vec::new();
-
-    for y in 0..((height as usize) - WINDOW_HEIGHT) {
-        for x in 0..((width as usize) - WINDOW_WIDTH) {
-            let mut mat = Matrix::zeros(WINDOW_HEIGHT, WINDOW_WIDTH);
-            let mut max = 1.0;
-
-            for i in 0..WINDOW_HEIGHT {
-                for j in 0..WINDOW_WIDTH {
-                    let pixel = data.get_pixel((x + i) as u32, (y + j) as u32);
-                    let mat_index = [i as usize, j as usize];
-                    let value = (pixel.channels()[0]) as f64;
-                    mat[mat_index] = value;
-
-                    if value > max {
-                        max = value;
-                    }
-                }
-            }
+    // merges the overlapping windows around each face
+    if let Some(min_neighbours) = args::get_option("min-neighbours") {
+        coll = group_detections(&coll, 0.3, min_neighbours);
+    }
 
-            mat = mat / max;
+    coll = params.keep_best(coll);
 
-            let integral_image = IntegralImage::build(&mat);
+    if let Some(landmarks_path) = args::get_option::<String>("landmarks") {
+        let regressor = LandmarkRegressor::load(&landmarks_path);
 
-            if ensemble.predict(&integral_image) {
-                coll.push((x, y, WINDOW_WIDTH, WINDOW_HEIGHT));
-            }
+        for detection in coll.iter_mut() {
+            detection.landmarks = Some(regressor.predict(&data, &detection.rect));
         }
     }
 
@@ -83,10 +97,34 @@ fn main() {
 
             piston_window::image(&tex, c.transform, g);
 
-            for &(x, y, ww, wh) in coll.iter() {
-                let rect = Rectangle::new_border([0.0, 1.0, 0.0, 0.2], 1.0);
-                let pos = [x as f64, y as f64, ww as f64, wh as f64];
-                rect.draw(pos, &c.draw_state, c.transform, g)
+            for detection in coll.iter() {
+                if let Some(ref rotation) = detection.rotation {
+                    // the turned box, edge by edge
+                    let corners = rotation.corners();
+
+                    for i in 0..corners.len() {
+                        let (a, b) = (corners[i], corners[(i + 1) % corners.len()]);
+                        piston_window::line([0.0, 1.0, 0.0, 0.2],
+                                            0.5,
+                                            [a.0, a.1, b.0, b.1],
+                                            c.transform,
+                                            g);
+                    }
+                } else {
+                    let rect = Rectangle::new_border([0.0, 1.0, 0.0, 0.2], 1.0);
+                    let pos = [detection.rect.x as f64,
+                               detection.rect.y as f64,
+                               detection.rect.width as f64,
+                               detection.rect.height as f64];
+                    rect.draw(pos, &c.draw_state, c.transform, g);
+                }
+
+                if let Some(ref landmarks) = detection.landmarks {
+                    let dot = Rectangle::new([1.0, 0.0, 0.0, 1.0]);
+                    for &(x, y) in landmarks.to_vec().iter() {
+                        dot.draw([x - 1.0, y - 1.0, 2.0, 2.0], &c.draw_state, c.transform, g);
+                    }
+                }
             }
         });
     }
@@ -0,0 +1,145 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_json;
+extern crate image;
+extern crate rulinalg;
+extern crate rand;
+
+mod load;
+mod pgm;
+mod manifest;
+mod synthetic;
+mod shared;
+mod geometry;
+mod integral_image;
+mod haar_like_feature;
+mod decision_tree;
+mod lbp_feature;
+mod weak_classifier;
+mod prediction_ensemble;
+mod soft_cascade;
+mod detector;
+mod fast_integral_image;
+mod compiled_cascade;
+mod linear_svm;
+mod hog;
+mod model;
+mod args;
+
+use std::fs;
+use image::{GrayImage, Luma};
+use rulinalg::matrix::Matrix;
+use load::get_data_from_args;
+use model::Model;
+use geometry::{Point, Rect};
+use shared::{ImageData, WINDOW_HEIGHT, WINDOW_WIDTH};
+
+const SCALE: usize = 8;
+const PADDING: usize = 4;
+const BACKGROUND: u8 = 128;
+
+// how strongly the rectangles cover the mean face
+const OVERLAY_OPACITY: f64 = 0.6;
+
+fn get_mean_face() -> ImageData {
+    let (image_collection, num_faces, _) = get_data_from_args("./data/trainset", "train");
+
+    let sum = image_collection.iter()
+        .filter(|data_point| data_point.label > 0.0)
+        .fold(Matrix::zeros(WINDOW_HEIGHT, WINDOW_WIDTH),
+              |acc, data_point| acc + &data_point.image_data);
+
+    sum / (num_faces as f64)
+}
+
+fn draw_tile(sheet: &mut GrayImage,
+             tile_x: usize,
+             tile_y: usize,
+             rectangles: &Vec<(Rect, f64)>,
+             mean_face: &Option<ImageData>) {
+    for y in 0..WINDOW_HEIGHT {
+        for x in 0..WINDOW_WIDTH {
+            let base = match *mean_face {
+                Some(ref face) => face[[y, x]] * 255.0,
+                None => BACKGROUND as f64,
+            };
+
+            let sign = rectangles.iter()
+                .find(|&&(rect, _)| rect.contains(Point::new(x, y)))
+                .map(|&(_, sign)| sign);
+
+            let value = match (sign, mean_face.is_some()) {
+                (Some(sign), true) => {
+                    let target = if sign > 0.0 { 255.0 } else { 0.0 };
+                    base * (1.0 - OVERLAY_OPACITY) + target * OVERLAY_OPACITY
+                }
+                (Some(sign), false) => if sign > 0.0 { 255.0 } else { 0.0 },
+                (None, _) => base,
+            };
+
+            let pixel = Luma([value as u8]);
+
+            for i in 0..SCALE {
+                for j in 0..SCALE {
+                    sheet.put_pixel((tile_x + x * SCALE + j) as u32,
+                                    (tile_y + y * SCALE + i) as u32,
+                                    pixel);
+                }
+            }
+        }
+    }
+}
+
+// tiles every feature of a stage into a single contact sheet
+fn draw_stage(features: &Vec<Vec<(Rect, f64)>>,
+              mean_face: &Option<ImageData>)
+              -> GrayImage {
+    let columns = (features.len() as f64).sqrt().ceil().max(1.0) as usize;
+    let rows = (features.len() + columns - 1) / columns;
+
+    let tile_width = WINDOW_WIDTH * SCALE + PADDING;
+    let tile_height = WINDOW_HEIGHT * SCALE + PADDING;
+
+    let mut sheet = GrayImage::from_pixel((columns * tile_width + PADDING) as u32,
+                                          (rows.max(1) * tile_height + PADDING) as u32,
+                                          Luma([255]));
+
+    for (i, rectangles) in features.iter().enumerate() {
+        draw_tile(&mut sheet,
+                  PADDING + (i % columns) * tile_width,
+                  PADDING + (i / columns) * tile_height,
+                  rectangles,
+                  mean_face);
+    }
+
+    sheet
+}
+
+fn main() {
+    let model_path = args::get_option("model").unwrap_or("./foo.json".to_string());
+    let ensemble = match Model::load(&model_path) {
+        Model::Cascade(ensemble) => ensemble,
+        _ => panic!("Only cascades of Haar-like or LBP features can be visualized"),
+    };
+
+    let output = args::get_option("output").unwrap_or("./features".to_string());
+    fs::create_dir_all(&output).expect("Unable to create output directory");
+
+    let mean_face = if args::get_flag("mean-face") {
+        Some(get_mean_face())
+    } else {
+        None
+    };
+
+    for (i, stage) in ensemble.stages().iter().enumerate() {
+        let features: Vec<_> = stage.iter()
+            .flat_map(|h| h.rectangles().into_iter())
+            .collect();
+
+        let path = format!("{}/stage_{:02}.png", output, i + 1);
+        draw_stage(&features, &mean_face).save(&path).expect("Unable to write image");
+
+        println!("Stage {}: {} features -> {}", i + 1, features.len(), path);
+    }
+}
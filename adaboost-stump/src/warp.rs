@@ -0,0 +1,145 @@
+use image::{GrayImage, Luma, Pixel};
+
+// luminosity at a sub-pixel position, replicating the border
+pub fn sample_bilinear(img: &GrayImage, x: f64, y: f64) -> f64 {
+    let (width, height) = img.dimensions();
+    let x = x.max(0.0).min((width - 1) as f64);
+    let y = y.max(0.0).min((height - 1) as f64);
+
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (fx, fy) = (x - x0 as f64, y - y0 as f64);
+
+    let value = |x, y| img.get_pixel(x, y).channels()[0] as f64;
+
+    let top = value(x0, y0) * (1.0 - fx) + value(x1, y0) * fx;
+    let bottom = value(x0, y1) * (1.0 - fx) + value(x1, y1) * fx;
+
+    top * (1.0 - fy) + bottom * fy
+}
+
+// resamples a square of side `size` rotated by `angle` radians around
+// `center` into a `width` x `height` patch; `anchor` is where the center
+// lands in the patch, as a fraction of its sides
+pub fn extract_patch(img: &GrayImage,
+                     center: (f64, f64),
+                     anchor: (f64, f64),
+                     size: f64,
+                     angle: f64,
+                     width: u32,
+                     height: u32)
+                     -> GrayImage {
+    let (sin, cos) = angle.sin_cos();
+    let scale = size / (width.max(height) as f64);
+
+    // average several samples per output pixel when shrinking
+    let subsamples = scale.ceil().max(1.0) as u32;
+
+    GrayImage::from_fn(width, height, |u, v| {
+        let mut sum = 0.0;
+
+        for i in 0..subsamples {
+            for j in 0..subsamples {
+                let px = (u as f64 + (j as f64 + 0.5) / subsamples as f64) * scale -
+                         anchor.0 * size;
+                let py = (v as f64 + (i as f64 + 0.5) / subsamples as f64) * scale -
+                         anchor.1 * size;
+
+                let x = center.0 + px * cos - py * sin;
+                let y = center.1 + px * sin + py * cos;
+
+                sum += sample_bilinear(img, x, y);
+            }
+        }
+
+        Luma([(sum / (subsamples * subsamples) as f64).round() as u8])
+    })
+}
+
+// a picture turned by `angle` radians around its centre, drawn on a canvas
+// large enough to hold all of it. a box that is upright on the canvas is
+// turned clockwise by `angle` in the picture
+#[derive(Debug, Copy, Clone)]
+pub struct Rotation {
+    angle: f64,
+    center: (f64, f64),
+    canvas_center: (f64, f64),
+    pub canvas_size: (u32, u32),
+}
+
+impl Rotation {
+    pub fn new(size: (u32, u32), angle: f64) -> Rotation {
+        let (sin, cos) = angle.sin_cos();
+        let (width, height) = (size.0 as f64, size.1 as f64);
+        // without the rounding errors of the sine and cosine, which would add a
+        // column at right angles
+        let side = |length: f64| ((length * 1e6).round() / 1e6).ceil() as u32;
+        let canvas_size = (side(width * cos.abs() + height * sin.abs()),
+                           side(width * sin.abs() + height * cos.abs()));
+
+        Rotation {
+            angle: angle,
+            center: (width / 2.0, height / 2.0),
+            canvas_center: (canvas_size.0 as f64 / 2.0, canvas_size.1 as f64 / 2.0),
+            canvas_size: canvas_size,
+        }
+    }
+
+    // position in the picture of a point of the canvas
+    pub fn to_original(&self, point: (f64, f64)) -> (f64, f64) {
+        let (sin, cos) = self.angle.sin_cos();
+        let (x, y) = (point.0 - self.canvas_center.0, point.1 - self.canvas_center.1);
+
+        (self.center.0 + x * cos - y * sin, self.center.1 + x * sin + y * cos)
+    }
+
+    // the canvas; corners outside the picture repeat its border
+    pub fn apply(&self, img: &GrayImage) -> GrayImage {
+        GrayImage::from_fn(self.canvas_size.0, self.canvas_size.1, |u, v| {
+            let (x, y) = self.to_original((u as f64 + 0.5, v as f64 + 0.5));
+            Luma([sample_bilinear(img, x - 0.5, y - 0.5).round() as u8])
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    fn close(a: (f64, f64), b: (f64, f64)) -> bool {
+        (a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9
+    }
+
+    #[test]
+    fn the_canvas_holds_the_turned_picture() {
+        assert_eq!(Rotation::new((40, 20), 0.0).canvas_size, (40, 20));
+        assert_eq!(Rotation::new((40, 20), PI / 2.0).canvas_size, (20, 40));
+        assert_eq!(Rotation::new((40, 20), PI).canvas_size, (40, 20));
+        assert_eq!(Rotation::new((10, 10), PI / 4.0).canvas_size, (15, 15));
+    }
+
+    #[test]
+    fn a_quarter_turn_maps_back_to_the_same_centre() {
+        let rotation = Rotation::new((40, 20), PI / 2.0);
+        assert!(close(rotation.to_original((10.0, 20.0)), (20.0, 10.0)));
+
+        // the centre of a box found on the canvas, and back again
+        let centre = rotation.to_original((5.0, 8.0));
+        assert!(close(centre, (32.0, 5.0)));
+
+        let back = Rotation::new(rotation.canvas_size, -PI / 2.0);
+        assert!(close(back.to_original(centre), (5.0, 8.0)));
+    }
+
+    #[test]
+    fn a_quarter_turn_moves_whole_pixels() {
+        let img = GrayImage::from_fn(4, 3, |x, y| Luma([(10 * y + x) as u8]));
+        let turned = Rotation::new((4, 3), PI / 2.0).apply(&img);
+
+        assert_eq!(turned.dimensions(), (3, 4));
+        for (u, v, pixel) in turned.enumerate_pixels() {
+            assert_eq!(pixel[0], img.get_pixel(3 - v, u)[0], "({}, {})", u, v);
+        }
+    }
+}
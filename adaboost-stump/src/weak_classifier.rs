@@ -0,0 +1,43 @@
+use integral_image::IntegralImage;
+use decision_tree::DecisionTree;
+use lbp_feature::LbpClassifier;
+use geometry::Rect;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum WeakClassifier {
+    Tree(DecisionTree),
+    Lbp(LbpClassifier),
+}
+
+impl WeakClassifier {
+    pub fn predict(&self, integral_image: &IntegralImage) -> f64 {
+        match *self {
+            WeakClassifier::Tree(ref tree) => tree.predict(integral_image),
+            WeakClassifier::Lbp(ref lbp) => lbp.predict(integral_image),
+        }
+    }
+
+    // boxes of every feature the classifier looks at, one list per feature
+    pub fn rectangles(&self) -> Vec<Vec<(Rect, f64)>> {
+        match *self {
+            WeakClassifier::Tree(ref tree) => {
+                tree.features().iter().map(|feature| feature.rectangles()).collect()
+            }
+            WeakClassifier::Lbp(ref lbp) => vec![lbp.feature().rectangles()],
+        }
+    }
+
+    pub fn weight(&self) -> f64 {
+        match *self {
+            WeakClassifier::Tree(ref tree) => tree.weight,
+            WeakClassifier::Lbp(ref lbp) => lbp.weight,
+        }
+    }
+
+    pub fn set_weight(&mut self, weight: f64) {
+        match *self {
+            WeakClassifier::Tree(ref mut tree) => tree.weight = weight,
+            WeakClassifier::Lbp(ref mut lbp) => lbp.weight = weight,
+        }
+    }
+}
@@ -0,0 +1,95 @@
+// the compiled cascades scan with integer sums and scaled thresholds, yet must
+// accept the same windows as the cascades evaluated window by window
+extern crate adaboost_stump;
+
+use adaboost_stump::boosting::{adaboost, FeaturePool};
+use adaboost_stump::compiled_cascade::WindowLayout;
+use adaboost_stump::detection_params::ScanLevel;
+use adaboost_stump::detector::Detector;
+use adaboost_stump::haar_like_feature::HaarLikeFeature;
+use adaboost_stump::prediction_ensemble::PredictionEnsemble;
+use adaboost_stump::scanner::{scan_compiled, scan_windows};
+use adaboost_stump::soft_cascade::SoftCascade;
+use adaboost_stump::synthetic::{seeded_rng, synthetic_dataset, synthetic_scene};
+
+// a few rounds are enough, the cascade only has to accept some windows
+const POOL_THINNING: usize = 31;
+
+fn feature_pool() -> FeaturePool {
+    FeaturePool::Haar(HaarLikeFeature::generate_all_features()
+        .into_iter()
+        .enumerate()
+        .filter(|&(i, _)| i % POOL_THINNING == 0)
+        .map(|(_, feature)| feature)
+        .collect())
+}
+
+// at the size of the model both scans see the same pixels; larger windows
+// shrink the picture in one and scale the boxes in the other, so only the
+// 19x19 windows can be expected to match exactly
+fn assert_same_windows<D: Detector>(detector: &D) {
+    for seed in 0..3 {
+        let scene = synthetic_scene(seed, 160, 120, 3, 19, 24);
+        let (width, height) = scene.image.dimensions();
+        let cascade = detector.compile(&WindowLayout::new(width as usize + 1, 1.0))
+            .expect("Haar cascades compile");
+
+        for &step in [1.0, 2.0].iter() {
+            let level = ScanLevel::new(1.0, step, step);
+
+            let windows = scan_windows(detector, &scene.image, &level, None);
+            let compiled = scan_compiled(&cascade, &scene.image, &level, None);
+
+            assert!(!windows.is_empty(), "scene {}: nothing to compare", seed);
+            assert_eq!(windows.iter().map(|detection| detection.rect).collect::<Vec<_>>(),
+                       compiled.iter().map(|detection| detection.rect).collect::<Vec<_>>(),
+                       "scene {} ({}x{}) with a step of {}",
+                       seed,
+                       width,
+                       height,
+                       step);
+        }
+    }
+}
+
+#[test]
+fn compiled_stages_accept_the_same_windows() {
+    let (image_collection, num_faces, num_non_faces) = synthetic_dataset(0, 100, 200);
+    let mut feature_pool = feature_pool();
+
+    let mut ensemble = PredictionEnsemble::new();
+    for &num_rounds in [3, 7].iter() {
+        ensemble.push(adaboost(num_rounds,
+                               1,
+                               1.0,
+                               false,
+                               true,
+                               1.0,
+                               &mut feature_pool,
+                               &image_collection,
+                               num_faces,
+                               num_non_faces,
+                               &mut seeded_rng(0)));
+    }
+
+    assert_same_windows(&ensemble);
+}
+
+#[test]
+fn compiled_soft_cascades_accept_the_same_windows() {
+    let (image_collection, num_faces, num_non_faces) = synthetic_dataset(0, 100, 200);
+
+    let classifiers = adaboost(10,
+                               1,
+                               1.0,
+                               false,
+                               false,
+                               1.0,
+                               &mut feature_pool(),
+                               &image_collection,
+                               num_faces,
+                               num_non_faces,
+                               &mut seeded_rng(0));
+
+    assert_same_windows(&SoftCascade::calibrate(classifiers, &image_collection, 0.99));
+}
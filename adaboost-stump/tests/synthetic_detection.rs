@@ -0,0 +1,99 @@
+// trains a small soft cascade on synthetic windows and looks for the faces
+// planted in synthetic scenes
+extern crate adaboost_stump;
+
+use adaboost_stump::boosting::{adaboost, FeaturePool};
+use adaboost_stump::detection_params::DetectionParams;
+use adaboost_stump::haar_like_feature::HaarLikeFeature;
+use adaboost_stump::soft_cascade::SoftCascade;
+use adaboost_stump::scanner::{group_detections, scan};
+use adaboost_stump::synthetic::{seeded_rng, synthetic_dataset, synthetic_scene};
+
+// one feature in this many is enough for the synthetic faces and keeps the
+// rounds quick without optimizations. the pool cycles through the feature
+// types and polarities every 10 features, so the step must not share a
+// factor with 10
+const POOL_THINNING: usize = 31;
+const NUM_ROUNDS: usize = 60;
+
+fn train_cascade() -> SoftCascade {
+    let (image_collection, num_faces, num_non_faces) = synthetic_dataset(0, 300, 600);
+
+    let features = HaarLikeFeature::generate_all_features()
+        .into_iter()
+        .enumerate()
+        .filter(|&(i, _)| i % POOL_THINNING == 0)
+        .map(|(_, feature)| feature)
+        .collect();
+    let mut feature_pool = FeaturePool::Haar(features);
+
+    let classifiers = adaboost(NUM_ROUNDS,
+                               1,
+                               1.0,
+                               false,
+                               false,
+                               1.0,
+                               &mut feature_pool,
+                               &image_collection,
+                               num_faces,
+                               num_non_faces,
+                               &mut seeded_rng(0));
+
+    SoftCascade::calibrate(classifiers, &image_collection, 0.99)
+}
+
+#[test]
+fn cascade_separates_unseen_windows() {
+    let cascade = train_cascade();
+    let (image_collection, num_faces, num_non_faces) = synthetic_dataset(1, 100, 100);
+
+    let found = image_collection.iter()
+        .filter(|data_point| data_point.label > 0.0)
+        .filter(|data_point| cascade.predict(&data_point.integral_image))
+        .count();
+    let false_positives = image_collection.iter()
+        .filter(|data_point| data_point.label < 0.0)
+        .filter(|data_point| cascade.predict(&data_point.integral_image))
+        .count();
+
+    assert!(found as f64 >= 0.85 * num_faces as f64,
+            "{} of {} faces found",
+            found,
+            num_faces);
+    assert!(false_positives as f64 <= 0.1 * num_non_faces as f64,
+            "{} of {} non-faces accepted",
+            false_positives,
+            num_non_faces);
+}
+
+#[test]
+fn detection_recovers_planted_faces() {
+    let cascade = train_cascade();
+
+    let params = DetectionParams {
+        max_size: Some(60),
+        ..DetectionParams::default()
+    };
+
+    for seed in 0..3 {
+        let scene = synthetic_scene(seed, 240, 180, 4, 24, 48);
+        let windows = scan(&cascade, &scene.image, None, &params);
+        let detections = group_detections(&windows, 0.3, 10);
+
+        for face in scene.faces.iter() {
+            assert!(detections.iter().any(|detection| detection.rect.overlap(face) >= 0.4),
+                    "scene {}: {:?} not found among {:?}",
+                    seed,
+                    face,
+                    detections.iter().map(|detection| detection.rect).collect::<Vec<_>>());
+        }
+
+        let false_positives = detections.iter()
+            .filter(|detection| scene.faces.iter().all(|face| detection.rect.overlap(face) < 0.4))
+            .count();
+        assert!(false_positives <= 1,
+                "scene {}: {} detections away from the faces",
+                seed,
+                false_positives);
+    }
+}